@@ -0,0 +1,131 @@
+use ip::{self, Entry};
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "t", rename_all = "snake_case")]
+pub enum Operation {
+    Release,
+    Delete,
+    Tag { tags: Vec<String> },
+    Owner { owner: String },
+}
+
+/// Kept in the confirmation modal until the user confirms.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Bulk {
+    pub ips: Vec<String>,
+    pub operation: Operation,
+    /// Selected IPs which were deleted before the selection was confirmed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")] pub gone: Vec<String>,
+}
+
+impl Operation {
+    /// On a missing argument, returns the block which should show the error.
+    pub fn new(
+        name: &str,
+        tags: Option<&str>,
+        owner: Option<&str>,
+    ) -> Result<Operation, (&'static str, &'static str)> {
+        match name {
+            "release" => Ok(Operation::Release),
+            "delete" => Ok(Operation::Delete),
+            "tag" => match tags.map(ip::parse_tags) {
+                Some(ref tags) if !tags.is_empty() => Ok(Operation::Tag { tags: tags.clone() }),
+                _ => Err(("tags", "추가할 태그를 입력해 주세요.")),
+            },
+            "owner" => match owner {
+                Some(owner) => Ok(Operation::Owner {
+                    owner: owner.to_owned(),
+                }),
+                None => Err(("owner", "새 담당자를 선택해 주세요.")),
+            },
+            _ => Err(("operation", "작업을 선택해 주세요.")),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Operation::Release => "release",
+            Operation::Delete => "delete",
+            Operation::Tag { .. } => "tag",
+            Operation::Owner { .. } => "owner",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match *self {
+            Operation::Release => "반납",
+            Operation::Delete => "삭제",
+            Operation::Tag { .. } => "태그 추가",
+            Operation::Owner { .. } => "담당자 변경",
+        }
+    }
+
+    /// Returns `None` when the entry is to be deleted.
+    pub fn apply(&self, entry: &Entry) -> Option<Entry> {
+        let mut entry = entry.clone();
+        match *self {
+            Operation::Release => {
+                entry.using = false;
+                entry.owner = None;
+            }
+            Operation::Delete => return None,
+            Operation::Tag { ref tags } => for tag in tags {
+                if !entry.tags.contains(tag) {
+                    entry.tags.push(tag.clone());
+                }
+            },
+            Operation::Owner { ref owner } => entry.owner = Some(owner.clone()),
+        }
+        entry.touch();
+        Some(entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn entry() -> Entry {
+        let mut entry = Entry::draft("10.0.0.1", Path::new("/nonexistent"));
+        entry.using = true;
+        entry.owner = Some("U1".to_owned());
+        entry.tags = vec!["gpu".to_owned()];
+        entry.updated = None;
+        entry
+    }
+
+    #[test]
+    fn new_reports_the_block_of_a_missing_argument() {
+        assert_eq!(Operation::new("release", None, None).unwrap().name(), "release");
+        assert_eq!(Operation::new("tag", Some("a, b"), None).unwrap().name(), "tag");
+        assert_eq!(Operation::new("tag", Some(" , "), None).err().unwrap().0, "tags");
+        assert_eq!(Operation::new("owner", None, None).err().unwrap().0, "owner");
+        assert_eq!(Operation::new("", None, None).err().unwrap().0, "operation");
+    }
+
+    #[test]
+    fn apply_changes_a_copy() {
+        let entry = entry();
+
+        let released = Operation::Release.apply(&entry).unwrap();
+        assert!(!released.using);
+        assert!(released.owner.is_none());
+        assert!(released.updated.is_some());
+
+        let tagged = Operation::Tag {
+            tags: vec!["gpu".to_owned(), "lab".to_owned()],
+        }.apply(&entry)
+            .unwrap();
+        assert_eq!(tagged.tags, vec!["gpu", "lab"]);
+
+        let transferred = Operation::Owner {
+            owner: "U2".to_owned(),
+        }.apply(&entry)
+            .unwrap();
+        assert_eq!(transferred.owner.unwrap(), "U2");
+
+        assert!(Operation::Delete.apply(&entry).is_none());
+        assert_eq!(entry.owner.unwrap(), "U1");
+    }
+}
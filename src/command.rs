@@ -0,0 +1,290 @@
+use std::net::Ipv4Addr;
+use Export;
+use page::{Group, Options, Sort, MAX_SIZE};
+use subnet::Subnet;
+
+pub enum Command {
+    Help(Option<String>),
+    List(Options),
+    Show(String),
+    Search(String, Options),
+    Add(String, Option<String>),
+    Release(String),
+    History(String),
+    Subnet(Option<String>),
+    Export(Export),
+}
+
+impl Command {
+    pub fn shareable(&self) -> bool {
+        match *self {
+            Command::List(_)
+            | Command::Show(_)
+            | Command::Search(..)
+            | Command::History(_)
+            | Command::Subnet(_) => true,
+            _ => false,
+        }
+    }
+}
+
+pub struct Usage {
+    pub name: &'static str,
+    pub synopsis: &'static str,
+    pub description: &'static str,
+}
+
+pub const USAGES: &[Usage] = &[
+    Usage {
+        name: "list",
+        synopsis: "list [--sort ip|domain|updated|owner] [--group subnet|tag|status] \
+                   [--size 1-10] [--share]",
+        description: "등록된 IP 목록을 보여줍니다. `--sort updated`는 최근 변경 순이고, \
+                      `--size`는 한 페이지에 보여줄 개수입니다.",
+    },
+    Usage {
+        name: "search",
+        synopsis: "search <검색어> [--sort ...] [--group ...] [--share]",
+        description: "IP, 도메인, 포트, 설명, 상태(사용중/미사용)로 검색합니다. \
+                      명령어가 아닌 입력은 모두 검색어로 처리됩니다.",
+    },
+    Usage {
+        name: "add",
+        synopsis: "add <IP> [설명]",
+        description: "새 IP를 등록하고 담당자로 지정합니다.",
+    },
+    Usage {
+        name: "release",
+        synopsis: "release <IP>",
+        description: "IP를 미사용으로 바꾸고 담당자를 비웁니다.",
+    },
+    Usage {
+        name: "history",
+        synopsis: "history <IP>",
+        description: "IP의 변경 기록을 보여줍니다.",
+    },
+    Usage {
+        name: "subnet",
+        synopsis: "subnet [서브넷]",
+        description: "서브넷 사용률을 보여줍니다. 서브넷을 지정하면 빈 IP도 보여줍니다.",
+    },
+    Usage {
+        name: "export",
+        synopsis: "export [hosts|ssh-config]",
+        description: "hosts 파일 또는 ssh config 형식으로 내보냅니다.",
+    },
+    Usage {
+        name: "help",
+        synopsis: "help [명령어]",
+        description: "도움말을 보여줍니다.",
+    },
+];
+
+/// The text is returned untouched unless the flag is in it.
+pub fn take_flag(text: &str, flag: &str) -> (String, bool) {
+    if !text.split_whitespace().any(|word| word == flag) {
+        return (text.to_owned(), false);
+    }
+    (
+        text.split_whitespace()
+            .filter(|word| *word != flag)
+            .collect::<Vec<_>>()
+            .join(" "),
+        true,
+    )
+}
+
+fn take_options(text: &str) -> Option<(String, Options)> {
+    let mut options = Options::default();
+    let mut words = vec![];
+    let mut iter = text.split_whitespace();
+    while let Some(word) = iter.next() {
+        match word {
+            "--sort" => options.sort = iter.next().and_then(Sort::from_name)?,
+            "--group" => options.group = Some(iter.next().and_then(Group::from_name)?),
+            "--size" => {
+                let size = iter.next().and_then(|size| size.parse().ok())?;
+                if size < 1 || size > MAX_SIZE {
+                    return None;
+                }
+                options.size = Some(size);
+            }
+            _ => words.push(word),
+        }
+    }
+    Some((words.join(" "), options))
+}
+
+pub fn is_ip(s: &str) -> bool {
+    s.parse::<Ipv4Addr>().is_ok()
+}
+
+/// Text whose arguments don't fit the subcommand it starts with is searched for instead.
+/// Only `list` and searches take options; other subcommands get their arguments verbatim.
+/// On misuse, returns the name of the subcommand whose usage should be shown.
+pub fn parse(text: &str) -> Result<Command, &'static str> {
+    let text = text.trim();
+    let mut split = text.splitn(2, char::is_whitespace);
+    let name = split.next().unwrap_or_default();
+    let rest = split.next().map(str::trim).filter(|s| !s.is_empty());
+    let command = match name {
+        "" | "list" | "search" => None,
+        "help" => match rest {
+            None => Some(Command::Help(None)),
+            Some(topic) if USAGES.iter().any(|usage| usage.name == topic) => {
+                Some(Command::Help(Some(topic.to_owned())))
+            }
+            Some(_) => None,
+        },
+        "add" => {
+            let mut split = rest.unwrap_or_default().splitn(2, char::is_whitespace);
+            match split.next() {
+                Some(ip) if is_ip(ip) => Some(Command::Add(
+                    ip.to_owned(),
+                    split
+                        .next()
+                        .map(str::trim)
+                        .filter(|s| !s.is_empty())
+                        .map(str::to_owned),
+                )),
+                _ => None,
+            }
+        }
+        "release" => rest.filter(|ip| is_ip(ip))
+            .map(|ip| Command::Release(ip.to_owned())),
+        "history" => rest.filter(|ip| is_ip(ip))
+            .map(|ip| Command::History(ip.to_owned())),
+        "subnet" => match rest {
+            None => Some(Command::Subnet(None)),
+            Some(subnet) if Subnet::from_str(subnet).is_ok() => {
+                Some(Command::Subnet(Some(subnet.to_owned())))
+            }
+            Some(_) => None,
+        },
+        "export" => match rest {
+            None | Some("hosts") => Some(Command::Export(Export::Hosts)),
+            Some("ssh-config") => Some(Command::Export(Export::SshConfig)),
+            _ => None,
+        },
+        _ if is_ip(text) => Some(Command::Show(text.to_owned())),
+        _ => None,
+    };
+    if let Some(command) = command {
+        return Ok(command);
+    }
+
+    let usage = if name == "list" { "list" } else { "search" };
+    let (text, options) = take_options(text).ok_or(usage)?;
+    let mut split = text.splitn(2, char::is_whitespace);
+    let name = split.next().unwrap_or_default();
+    let rest = split.next().map(str::to_owned);
+    Ok(match (name, rest) {
+        ("", _) | ("list", None) | ("search", None) => Command::List(options),
+        ("search", Some(query)) => Command::Search(query, options),
+        _ => Command::Search(text.clone(), options),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn search_query(text: &str) -> Option<String> {
+        match parse(text) {
+            Ok(Command::Search(query, _)) => Some(query),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn subcommands_parse_their_arguments() {
+        match parse("add 10.0.0.1 웹 서버") {
+            Ok(Command::Add(ip, description)) => {
+                assert_eq!(ip, "10.0.0.1");
+                assert_eq!(description.as_ref().map(String::as_str), Some("웹 서버"));
+            }
+            _ => panic!("expected add"),
+        }
+        match parse("history 10.0.0.1") {
+            Ok(Command::History(ip)) => assert_eq!(ip, "10.0.0.1"),
+            _ => panic!("expected history"),
+        }
+        match parse("help add") {
+            Ok(Command::Help(Some(topic))) => assert_eq!(topic, "add"),
+            _ => panic!("expected help"),
+        }
+        match parse("10.0.0.1") {
+            Ok(Command::Show(ip)) => assert_eq!(ip, "10.0.0.1"),
+            _ => panic!("expected show"),
+        }
+        match parse("export ssh-config") {
+            Ok(Command::Export(Export::SshConfig)) => {}
+            _ => panic!("expected export"),
+        }
+    }
+
+    #[test]
+    fn keywords_without_valid_arguments_are_searched() {
+        for text in &[
+            "add",
+            "add server",
+            "release web",
+            "history of gpu",
+            "help desk",
+            "subnet mask",
+            "export csv",
+        ] {
+            assert_eq!(search_query(text).as_ref().map(String::as_str), Some(*text));
+        }
+        assert_eq!(search_query("gpu server").unwrap(), "gpu server");
+    }
+
+    #[test]
+    fn size_applies_to_a_single_list() {
+        match parse("list --size 3") {
+            Ok(Command::List(options)) => assert_eq!(options.size(8), 3),
+            _ => panic!("expected list"),
+        }
+        match parse("search gpu --size 10") {
+            Ok(Command::Search(query, options)) => {
+                assert_eq!(query, "gpu");
+                assert_eq!(options.size(8), 10);
+            }
+            _ => panic!("expected search"),
+        }
+        assert!(parse("list --size 0").is_err());
+        assert!(parse("list --size 11").is_err());
+        assert!(parse("list --size").is_err());
+    }
+
+    #[test]
+    fn options_are_only_taken_by_list_and_search() {
+        match parse("add 10.0.0.1 foo  --sort bar") {
+            Ok(Command::Add(_, description)) => {
+                assert_eq!(description.unwrap(), "foo  --sort bar");
+            }
+            _ => panic!("expected add"),
+        }
+        match parse("search gpu --sort updated --group tag") {
+            Ok(Command::Search(query, options)) => {
+                assert_eq!(query, "gpu");
+                assert!(match options.sort {
+                    Sort::Updated => true,
+                    _ => false,
+                });
+                assert!(options.group.is_some());
+            }
+            _ => panic!("expected search"),
+        }
+        assert_eq!(parse("search gpu --sort x").err(), Some("search"));
+        assert_eq!(parse("gpu --group x").err(), Some("search"));
+        assert_eq!(parse("list --sort x").err(), Some("list"));
+    }
+
+    #[test]
+    fn take_flag_keeps_text_without_the_flag() {
+        let text = "add 10.0.0.1 a  b";
+        assert_eq!(take_flag(text, "--share"), (text.to_owned(), false));
+        assert_eq!(take_flag("list  --share", "--share"), ("list".to_owned(), true));
+    }
+}
@@ -0,0 +1,122 @@
+extern crate toml;
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::channel;
+use std::thread;
+use std::time::Duration;
+use errors::{self, Result};
+use ip::{self, Entry};
+use probe;
+use settings::{DiscoverySettings, ProbeSettings};
+use slack;
+use subnet::Subnet;
+use worker::Pool;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Candidate {
+    pub ip: String,
+    pub first_seen: u64,
+    pub last_seen: u64,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Candidates {
+    #[serde(default)] candidates: Vec<Candidate>,
+}
+
+pub fn load(path: &Path) -> Vec<Candidate> {
+    let mut content = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut content))
+        .ok()
+        .and_then(|_| toml::from_str::<Candidates>(&content).ok())
+        .map(|c| c.candidates)
+        .unwrap_or_default()
+}
+
+fn save(path: &Path, candidates: Vec<Candidate>) -> Result<()> {
+    let s = toml::to_string_pretty(&Candidates { candidates })?;
+    File::create(path)?.write_all(s.as_bytes())?;
+    Ok(())
+}
+
+pub fn sweep(
+    subnets: &[Subnet],
+    data_path: &Path,
+    settings: &DiscoverySettings,
+    probe_settings: &ProbeSettings,
+) -> Result<Vec<Candidate>> {
+    let now = probe::now();
+    let mut candidates = load(&settings.path)
+        .into_iter()
+        .filter(|c| Entry::from_ip(&c.ip, data_path).is_none())
+        .collect::<Vec<_>>();
+    let pool = Pool::new(settings.concurrency);
+    let (sender, receiver) = channel();
+    for host in subnets.iter().flat_map(Subnet::hosts) {
+        if Entry::from_ip(&host, data_path).is_some() {
+            continue;
+        }
+        let (sender, ports, probe_settings) =
+            (sender.clone(), settings.ports.clone(), probe_settings.clone());
+        pool.execute(move || {
+            let responding = host.parse::<IpAddr>()
+                .ok()
+                .and_then(|ip| probe::probe(&ip, &ports, &probe_settings).responding)
+                .unwrap_or(false);
+            let _ = sender.send((host, responding));
+        });
+    }
+    drop(sender);
+
+    let mut found = vec![];
+    let mut responding = receiver
+        .iter()
+        .filter(|&(_, responding)| responding)
+        .map(|(host, _)| host)
+        .collect::<Vec<_>>();
+    responding.sort_unstable_by_key(|host| ip::ip_key(host));
+    for host in responding {
+        if let Some(c) = candidates.iter_mut().find(|c| c.ip == host) {
+            c.last_seen = now;
+            continue;
+        }
+        let c = Candidate {
+            ip: host,
+            first_seen: now,
+            last_seen: now,
+        };
+        found.push(c.clone());
+        candidates.push(c);
+    }
+    save(&settings.path, candidates)?;
+    Ok(found)
+}
+
+pub fn spawn(
+    subnets: Vec<Subnet>,
+    data_path: PathBuf,
+    settings: DiscoverySettings,
+    probe_settings: ProbeSettings,
+    token: String,
+) {
+    thread::spawn(move || loop {
+        match sweep(&subnets, &data_path, &settings, &probe_settings) {
+            Ok(ref found) if !found.is_empty() && !settings.channel.is_empty() => {
+                if let Err(e) = slack::post_message(
+                    &settings.channel,
+                    &slack::message::generate_discovery_message(found),
+                    &token,
+                ) {
+                    errors::log("failed to report discovered IPs", &e);
+                }
+            }
+            Ok(_) => (),
+            Err(e) => errors::log("failed to sweep subnets", &e),
+        }
+        thread::sleep(Duration::from_secs(settings.interval));
+    });
+}
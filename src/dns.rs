@@ -0,0 +1,396 @@
+use std::collections::hash_map::RandomState;
+use std::fs::File;
+use std::hash::{BuildHasher, Hasher};
+use std::io::Read;
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+use errors::{self, Result};
+use ip::{self, Entry};
+use probe;
+use settings::DnsSettings;
+use slack;
+
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const CLASS_IN: u16 = 1;
+
+pub struct Resolver {
+    server: SocketAddr,
+    timeout: Duration,
+}
+
+fn read_u16(buf: &[u8], pos: usize) -> Result<u16> {
+    if pos + 2 > buf.len() {
+        bail!("truncated DNS message");
+    }
+    Ok((u16::from(buf[pos]) << 8) | u16::from(buf[pos + 1]))
+}
+
+fn read_name(buf: &[u8], mut pos: usize) -> Result<(String, usize)> {
+    let mut labels = vec![];
+    let mut end = None;
+    let mut jumps = 0;
+    loop {
+        let len = *buf.get(pos).ok_or("truncated DNS name")? as usize;
+        if len & 0xC0 == 0xC0 {
+            let offset = (read_u16(buf, pos)? & 0x3FFF) as usize;
+            if end.is_none() {
+                end = Some(pos + 2);
+            }
+            jumps += 1;
+            if jumps > 64 {
+                bail!("DNS name compression loop");
+            }
+            pos = offset;
+        } else if len == 0 {
+            return Ok((labels.join("."), end.unwrap_or(pos + 1)));
+        } else {
+            let label = buf.get(pos + 1..pos + 1 + len).ok_or("truncated DNS label")?;
+            labels.push(String::from_utf8_lossy(label).into_owned());
+            pos += 1 + len;
+        }
+    }
+}
+
+fn build_query(id: u16, name: &str, qtype: u16) -> Result<Vec<u8>> {
+    let mut query = vec![(id >> 8) as u8, id as u8, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+    for label in name.trim_right_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            bail!("invalid DNS label in {}", name);
+        }
+        query.push(label.len() as u8);
+        query.extend(label.as_bytes());
+    }
+    query.push(0);
+    if query.len() - 12 > 255 {
+        bail!("DNS name too long: {}", name);
+    }
+    query.extend(&[(qtype >> 8) as u8, qtype as u8, (CLASS_IN >> 8) as u8, CLASS_IN as u8]);
+    Ok(query)
+}
+
+/// `RandomState` is keyed from the OS, so the ID cannot be guessed by a spoofer.
+fn random_id() -> u16 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(probe::now());
+    hasher.finish() as u16
+}
+
+impl Resolver {
+    pub fn new(server: Option<&str>, timeout: Duration) -> Result<Self> {
+        let server = match server {
+            Some(server) => server.to_owned(),
+            None => system_nameserver().ok_or("no nameserver configured")?,
+        };
+        let server = server
+            .parse::<SocketAddr>()
+            .or_else(|_| format!("{}:53", server).parse::<SocketAddr>())
+            .map_err(|_| format!("invalid resolver address: {}", server))?;
+        Ok(Resolver { server, timeout })
+    }
+
+    fn query(&self, name: &str, qtype: u16) -> Result<Vec<Vec<u8>>> {
+        let socket = UdpSocket::bind(if self.server.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        })?;
+        socket.set_read_timeout(Some(self.timeout))?;
+        let id = random_id();
+        socket.send_to(&build_query(id, name, qtype)?, self.server)?;
+
+        let mut buf = [0u8; 4096];
+        let len = loop {
+            let (len, from) = socket.recv_from(&mut buf)?;
+            if from == self.server && len >= 12 && read_u16(&buf, 0)? == id {
+                break len;
+            }
+        };
+        let buf = &buf[..len];
+
+        if buf[2] & 0x02 != 0 {
+            bail!("truncated DNS response");
+        }
+        match buf[3] & 0x0F {
+            0 | 3 => (),
+            rcode => bail!("DNS server returned rcode {}", rcode),
+        }
+        let qdcount = read_u16(buf, 4)?;
+        let ancount = read_u16(buf, 6)?;
+        let mut pos = 12;
+        for _ in 0..qdcount {
+            pos = read_name(buf, pos)?.1 + 4;
+        }
+        let mut answers = vec![];
+        for _ in 0..ancount {
+            pos = read_name(buf, pos)?.1;
+            let rtype = read_u16(buf, pos)?;
+            let rdlength = read_u16(buf, pos + 8)? as usize;
+            pos += 10;
+            if pos + rdlength > buf.len() {
+                bail!("truncated DNS record");
+            }
+            if rtype == qtype {
+                if qtype == TYPE_PTR {
+                    answers.push(read_name(buf, pos)?.0.into_bytes());
+                } else {
+                    answers.push(buf[pos..pos + rdlength].to_vec());
+                }
+            }
+            pos += rdlength;
+        }
+        Ok(answers)
+    }
+
+    pub fn lookup(&self, domain: &str) -> Result<Vec<String>> {
+        Ok(self.query(domain, TYPE_A)?
+            .into_iter()
+            .filter(|rdata| rdata.len() == 4)
+            .map(|rdata| format!("{}", Ipv4Addr::new(rdata[0], rdata[1], rdata[2], rdata[3])))
+            .collect())
+    }
+
+    pub fn reverse(&self, ip: &str) -> Result<Vec<String>> {
+        let ip = ip.parse::<Ipv4Addr>()
+            .map_err(|_| format!("invalid IPv4 address: {}", ip))?;
+        let o = ip.octets();
+        let name = format!("{}.{}.{}.{}.in-addr.arpa", o[3], o[2], o[1], o[0]);
+        Ok(self.query(&name, TYPE_PTR)?
+            .into_iter()
+            .map(|rdata| String::from_utf8_lossy(&rdata).into_owned())
+            .collect())
+    }
+}
+
+fn system_nameserver() -> Option<String> {
+    let mut content = String::new();
+    File::open("/etc/resolv.conf")
+        .and_then(|mut file| file.read_to_string(&mut content))
+        .ok()?;
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            if words.next() == Some("nameserver") {
+                words.next().map(|s| s.to_owned())
+            } else {
+                None
+            }
+        })
+        .next()
+}
+
+fn same_name(a: &str, b: &str) -> bool {
+    a.trim_right_matches('.')
+        .eq_ignore_ascii_case(b.trim_right_matches('.'))
+}
+
+pub fn check(entry: &Entry, resolver: &Resolver) -> Result<Vec<String>> {
+    let domain = match entry.domain {
+        Some(ref domain) if !domain.trim().is_empty() => domain.trim(),
+        _ => return Ok(vec![]),
+    };
+    let mut issues = vec![];
+
+    let ips = resolver.lookup(domain)?;
+    if ips.is_empty() {
+        issues.push(format!("{}이(가) 조회되지 않습니다", domain));
+    } else if !ips.contains(&entry.ip) {
+        issues.push(format!("{}이(가) 다른 IP({})로 조회됩니다", domain, ips.join(", ")));
+    }
+
+    let names = resolver.reverse(&entry.ip)?;
+    if names.is_empty() {
+        issues.push("역방향 조회(PTR) 결과가 없습니다".to_owned());
+    } else if !names.iter().any(|name| same_name(name, domain)) {
+        issues.push(format!("PTR이 {}을(를) 가리킵니다", names.join(", ")));
+    }
+
+    Ok(issues)
+}
+
+fn check_all(data_path: &Path, resolver: &Resolver) -> Vec<Entry> {
+    Entry::list(data_path)
+        .into_iter()
+        .filter_map(|entry| {
+            let issues = check(&entry, resolver).ok()?;
+            let store = ip::lock();
+            let mut entry = Entry::from_ip(&entry.ip, data_path)?;
+            if entry.dns_issues != issues {
+                entry.dns_issues = issues;
+                if let Err(e) = store.commit(&[(Some(&entry), Some(&entry))]) {
+                    errors::log(&format!("failed to save DNS issues of {}", entry.ip), &e);
+                    return None;
+                }
+            }
+            Some(entry)
+        })
+        .filter(|entry| !entry.dns_issues.is_empty())
+        .collect()
+}
+
+pub fn spawn(data_path: PathBuf, settings: DnsSettings, resolver: Resolver, token: String) {
+    let mut reported = vec![];
+    thread::spawn(move || loop {
+        let entries = check_all(&data_path, &resolver);
+        let issues = entries
+            .iter()
+            .map(|entry| (entry.ip.clone(), entry.dns_issues.clone()))
+            .collect::<Vec<_>>();
+        // The same issues are reported only once.
+        let changed = issues != reported;
+        reported = issues;
+        if changed && !entries.is_empty() && !settings.channel.is_empty() {
+            if let Err(e) = slack::post_message(
+                &settings.channel,
+                &slack::message::generate_dns_report_message(&entries),
+                &token,
+            ) {
+                errors::log("failed to report DNS issues", &e);
+            }
+        }
+        thread::sleep(Duration::from_secs(settings.interval));
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Answers every query it receives with each of `responses(query)` in turn, until no query
+    /// arrives for a while.
+    fn stub<F>(mut responses: F) -> Resolver
+    where
+        F: FnMut(&[u8]) -> Vec<Vec<u8>> + Send + 'static,
+    {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let server = socket.local_addr().unwrap();
+        thread::spawn(move || {
+            let mut buf = [0u8; 512];
+            while let Ok((len, from)) = socket.recv_from(&mut buf) {
+                for response in responses(&buf[..len]) {
+                    socket.send_to(&response, from).unwrap();
+                }
+            }
+        });
+        Resolver {
+            server,
+            timeout: Duration::from_secs(2),
+        }
+    }
+
+    fn response(query: &[u8], flags: u8, rcode: u8, ancount: u8, answers: &[u8]) -> Vec<u8> {
+        let mut response = query.to_vec();
+        response[2] = 0x80 | flags;
+        response[3] = 0x80 | rcode;
+        response[7] = ancount;
+        response.extend(answers);
+        response
+    }
+
+    /// An A record whose owner name is a compression pointer to the question.
+    const ANSWER: &[u8] = &[0xC0, 12, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4, 10, 0, 0, 1];
+
+    fn a_answer(ip: [u8; 4]) -> Vec<u8> {
+        let mut answer = ANSWER[..12].to_vec();
+        answer.extend(&ip);
+        answer
+    }
+
+    fn ptr_answer(name: &str) -> Vec<u8> {
+        let mut rdata = vec![];
+        for label in name.split('.') {
+            rdata.push(label.len() as u8);
+            rdata.extend(label.as_bytes());
+        }
+        rdata.push(0);
+        let mut answer = vec![0xC0, 12, 0, TYPE_PTR as u8, 0, 1, 0, 0, 0, 60, 0, rdata.len() as u8];
+        answer.extend(rdata);
+        answer
+    }
+
+    /// Answers A queries with `a` and PTR queries with `ptr`; `None` is NXDOMAIN.
+    fn zone(a: Option<[u8; 4]>, ptr: Option<&'static str>) -> Resolver {
+        stub(move |query| {
+            let answer = if read_u16(query, query.len() - 4).unwrap() == TYPE_PTR {
+                ptr.map(ptr_answer)
+            } else {
+                a.map(a_answer)
+            };
+            vec![match answer {
+                Some(answer) => response(query, 0x01, 0, 1, &answer),
+                None => response(query, 0x01, 3, 0, &[]),
+            }]
+        })
+    }
+
+    #[test]
+    fn check_classifies_mismatches() {
+        let mut entry = Entry::draft("10.0.0.1", Path::new("."));
+        entry.domain = Some("host.example.com".to_owned());
+        let check = |resolver| check(&entry, &resolver).unwrap();
+
+        assert!(check(zone(Some([10, 0, 0, 1]), Some("host.example.com"))).is_empty());
+        assert!(check(zone(Some([10, 0, 0, 1]), Some("HOST.example.com."))).is_empty());
+        assert_eq!(
+            check(zone(None, Some("host.example.com"))),
+            vec!["host.example.com이(가) 조회되지 않습니다"]
+        );
+        assert_eq!(
+            check(zone(Some([10, 0, 0, 2]), Some("host.example.com"))),
+            vec!["host.example.com이(가) 다른 IP(10.0.0.2)로 조회됩니다"]
+        );
+        assert_eq!(
+            check(zone(Some([10, 0, 0, 1]), Some("other.example.com"))),
+            vec!["PTR이 other.example.com을(를) 가리킵니다"]
+        );
+        assert_eq!(
+            check(zone(Some([10, 0, 0, 1]), None)),
+            vec!["역방향 조회(PTR) 결과가 없습니다"]
+        );
+    }
+
+    #[test]
+    fn lookup_follows_compression_pointers() {
+        let resolver = stub(|query| vec![response(query, 0x01, 0, 1, ANSWER)]);
+        assert_eq!(resolver.lookup("example.com").unwrap(), vec!["10.0.0.1"]);
+    }
+
+    #[test]
+    fn lookup_ignores_mismatched_ids() {
+        let resolver = stub(|query| {
+            let mut spoofed = response(query, 0x01, 0, 1, ANSWER);
+            spoofed[1] ^= 0xFF;
+            let last = spoofed.len() - 1;
+            spoofed[last] = 66;
+            vec![spoofed, response(query, 0x01, 0, 1, ANSWER)]
+        });
+        assert_eq!(resolver.lookup("example.com").unwrap(), vec!["10.0.0.1"]);
+    }
+
+    #[test]
+    fn nxdomain_has_no_answers() {
+        let resolver = stub(|query| vec![response(query, 0x01, 3, 0, &[])]);
+        assert!(resolver.lookup("missing.example.com").unwrap().is_empty());
+    }
+
+    #[test]
+    fn truncated_responses_are_errors() {
+        let resolver = stub(|query| vec![response(query, 0x03, 0, 1, ANSWER)]);
+        assert!(resolver.lookup("example.com").is_err());
+        let resolver = stub(|query| vec![response(query, 0x01, 0, 1, &ANSWER[..14])]);
+        assert!(resolver.lookup("example.com").is_err());
+    }
+
+    #[test]
+    fn build_query_rejects_long_names() {
+        assert!(build_query(1, &"a".repeat(63), TYPE_A).is_ok());
+        assert!(build_query(1, &"a".repeat(64), TYPE_A).is_err());
+        assert!(build_query(1, "a..example.com", TYPE_A).is_err());
+        let name = vec!["a".repeat(60); 5].join(".");
+        assert!(build_query(1, &name, TYPE_A).is_err());
+    }
+}
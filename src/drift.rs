@@ -0,0 +1,142 @@
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+use errors;
+use ip::{self, Entry};
+use probe;
+use settings::DriftSettings;
+use slack;
+
+pub struct Drift {
+    pub undeclared: Vec<u32>,
+    pub closed: Vec<u32>,
+}
+
+impl Drift {
+    pub fn is_empty(&self) -> bool {
+        self.undeclared.is_empty() && self.closed.is_empty()
+    }
+}
+
+pub fn detect(entry: &Entry, default_ports: &[u32], timeout: Duration) -> Option<Drift> {
+    let ip = entry.ip.parse::<IpAddr>().ok()?;
+    let mut ports = if entry.scan_ports.is_empty() {
+        default_ports.to_vec()
+    } else {
+        entry.scan_ports.clone()
+    };
+    let declared = entry
+        .open_ports
+        .iter()
+        .filter(|port| !ports.contains(port))
+        .cloned()
+        .collect::<Vec<_>>();
+    ports.extend(declared);
+    let open = probe::scan_ports(&ip, &ports, timeout);
+    Some(Drift {
+        undeclared: open.iter()
+            .filter(|port| !entry.open_ports.contains(port))
+            .filter(|port| !entry.ignored_ports.contains(port))
+            .cloned()
+            .collect(),
+        closed: entry
+            .open_ports
+            .iter()
+            .filter(|port| !open.contains(port))
+            .filter(|port| !entry.ignored_ports.contains(port))
+            .cloned()
+            .collect(),
+    })
+}
+
+pub fn pending(entry: &Entry) -> Drift {
+    Drift {
+        undeclared: entry
+            .reported_ports
+            .iter()
+            .filter(|port| !entry.open_ports.contains(port))
+            .cloned()
+            .collect(),
+        closed: entry
+            .reported_ports
+            .iter()
+            .filter(|port| entry.open_ports.contains(port))
+            .cloned()
+            .collect(),
+    }
+}
+
+fn check_all(data_path: &Path, settings: &DriftSettings, timeout: Duration, token: &str) {
+    for entry in Entry::list(data_path) {
+        let drift = match detect(&entry, &settings.ports, timeout) {
+            Some(drift) => drift,
+            None => continue,
+        };
+        let store = ip::lock();
+        let mut entry = match Entry::from_ip(&entry.ip, data_path) {
+            Some(entry) => entry,
+            None => continue,
+        };
+        let is_new = drift
+            .undeclared
+            .iter()
+            .chain(drift.closed.iter())
+            .any(|port| !entry.reported_ports.contains(port));
+        let reported = drift
+            .undeclared
+            .iter()
+            .chain(drift.closed.iter())
+            .cloned()
+            .collect::<Vec<_>>();
+        if reported != entry.reported_ports {
+            entry.reported_ports = reported;
+            if let Err(e) = store.commit(&[(Some(&entry), Some(&entry))]) {
+                errors::log(&format!("failed to save drift of {}", entry.ip), &e);
+                continue;
+            }
+        }
+        drop(store);
+        if is_new && !drift.is_empty() {
+            if let Err(e) = slack::post_message(
+                &settings.channel,
+                &slack::message::generate_drift_message(&entry.ip, &drift),
+                token,
+            ) {
+                errors::log(&format!("failed to report drift of {}", entry.ip), &e);
+            }
+        }
+    }
+}
+
+pub fn spawn(data_path: PathBuf, settings: DriftSettings, timeout: Duration, token: String) {
+    thread::spawn(move || loop {
+        check_all(&data_path, &settings, timeout, &token);
+        thread::sleep(Duration::from_secs(settings.interval));
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn detect_reports_localhost_listeners() {
+        let open = TcpListener::bind("127.0.0.1:0").unwrap();
+        let open_port = u32::from(open.local_addr().unwrap().port());
+        let closed_port = {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            u32::from(listener.local_addr().unwrap().port())
+        };
+        let mut entry = Entry::draft("127.0.0.1", Path::new("."));
+        entry.open_ports = vec![closed_port];
+        let drift = detect(&entry, &[open_port], Duration::from_millis(500)).unwrap();
+        assert_eq!(drift.undeclared, vec![open_port]);
+        assert_eq!(drift.closed, vec![closed_port]);
+
+        entry.ignored_ports = vec![open_port, closed_port];
+        let drift = detect(&entry, &[open_port], Duration::from_millis(500)).unwrap();
+        assert!(drift.is_empty());
+    }
+}
@@ -14,3 +14,10 @@ error_chain!{
         TomlSer(self::toml::ser::Error);
     }
 }
+
+/// Errors of work which has no caller left to return them to, such as background jobs and
+/// delayed responses, are reported here.
+pub fn log(context: &str, error: &Error) {
+    let causes = error.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+    eprintln!("{}: {}", context, causes.join(": "));
+}
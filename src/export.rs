@@ -0,0 +1,107 @@
+use ip::{self, Entry};
+
+/// Domains which aren't valid, e.g. ones holding a newline, would break out of their line.
+fn exported(entries: &[Entry]) -> Vec<(&Entry, &str)> {
+    entries
+        .iter()
+        .filter(|entry| entry.using)
+        .filter_map(|entry| {
+            entry
+                .domain
+                .as_ref()
+                .map(|domain| domain.trim())
+                .filter(|domain| ip::is_valid_domain(domain))
+                .map(|domain| (entry, domain))
+        })
+        .collect()
+}
+
+fn short_name(domain: &str) -> Option<&str> {
+    domain
+        .split('.')
+        .next()
+        .filter(|name| !name.is_empty() && *name != domain)
+}
+
+fn ssh_port(entry: &Entry, ssh_ports: &[u32]) -> Option<u32> {
+    if entry.open_ports.contains(&22) {
+        Some(22)
+    } else {
+        entry
+            .open_ports
+            .iter()
+            .find(|port| ssh_ports.contains(port))
+            .cloned()
+    }
+}
+
+pub fn generate_hosts(entries: &[Entry]) -> String {
+    exported(entries)
+        .into_iter()
+        .map(|(entry, domain)| match short_name(domain) {
+            Some(name) => format!("{}\t{} {}\n", entry.ip, domain, name),
+            None => format!("{}\t{}\n", entry.ip, domain),
+        })
+        .collect()
+}
+
+pub fn generate_ssh_config(entries: &[Entry], ssh_ports: &[u32]) -> String {
+    exported(entries)
+        .into_iter()
+        .filter_map(|(entry, domain)| {
+            ssh_port(entry, ssh_ports).map(|port| {
+                let mut block = format!("Host {}", domain);
+                if let Some(name) = short_name(domain) {
+                    block += &format!(" {}", name);
+                }
+                block += &format!("\n    HostName {}\n", entry.ip);
+                if port != 22 {
+                    block += &format!("    Port {}\n", port);
+                }
+                block
+            })
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn entry(ip: &str, domain: &str, using: bool, open_ports: Vec<u32>) -> Entry {
+        let mut entry = Entry::draft(ip, Path::new("/nonexistent"));
+        entry.domain = Some(domain.to_owned());
+        entry.using = using;
+        entry.open_ports = open_ports;
+        entry
+    }
+
+    fn entries() -> Vec<Entry> {
+        vec![
+            entry("10.0.0.1", "web.example.com", true, vec![22, 80]),
+            entry("10.0.0.2", "gpu", true, vec![2222]),
+            entry("10.0.0.3", "old.example.com", false, vec![22]),
+            entry("10.0.0.4", "db.example.com", true, vec![5432]),
+            entry("10.0.0.5", "evil\n    ProxyCommand sh", true, vec![22]),
+        ]
+    }
+
+    #[test]
+    fn hosts_lists_entries_in_use_with_short_names() {
+        assert_eq!(
+            generate_hosts(&entries()),
+            "10.0.0.1\tweb.example.com web\n10.0.0.2\tgpu\n10.0.0.4\tdb.example.com db\n"
+        );
+    }
+
+    #[test]
+    fn ssh_config_uses_ssh_ports() {
+        assert_eq!(
+            generate_ssh_config(&entries(), &[2222]),
+            "Host web.example.com web\n    HostName 10.0.0.1\n\n\
+             Host gpu\n    HostName 10.0.0.2\n    Port 2222\n"
+        );
+    }
+}
@@ -0,0 +1,86 @@
+extern crate serde_json;
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use errors::Result;
+use ip::Entry;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Change {
+    pub field: String,
+    #[serde(default)] pub old: Option<String>,
+    #[serde(default)] pub new: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Record {
+    pub time: u64,
+    pub ip: String,
+    pub user: String,
+    pub action: String,
+    #[serde(default)] pub changes: Vec<Change>,
+    #[serde(default, skip_serializing_if = "Option::is_none")] pub attempted: Option<String>,
+}
+
+const FIELDS: &[&str] = &[
+    "domain",
+    "using",
+    "open_ports",
+    "description",
+    "owner",
+    "tags",
+];
+
+fn values(entry: Option<&Entry>) -> Vec<Option<String>> {
+    match entry {
+        Some(entry) => vec![
+            entry.domain.clone(),
+            Some(if entry.using { "사용중" } else { "미사용" }.to_owned()),
+            Some(
+                entry
+                    .open_ports
+                    .iter()
+                    .map(|port| format!("{}", port))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ).filter(|ports| !ports.is_empty()),
+            entry.description.clone(),
+            entry.owner.clone(),
+            Some(entry.tags.join(", ")).filter(|tags| !tags.is_empty()),
+        ],
+        None => vec![None; FIELDS.len()],
+    }
+}
+
+pub fn diff(old: Option<&Entry>, new: Option<&Entry>) -> Vec<Change> {
+    FIELDS
+        .iter()
+        .zip(values(old).into_iter().zip(values(new)))
+        .filter(|&(_, (ref old, ref new))| old != new)
+        .map(|(field, (old, new))| Change {
+            field: (*field).to_owned(),
+            old,
+            new,
+        })
+        .collect()
+}
+
+pub fn record(path: &Path, record: &Record) -> Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(record)?)?;
+    Ok(())
+}
+
+pub fn load(path: &Path, ip: &str) -> Vec<Record> {
+    File::open(path)
+        .map(|file| {
+            BufReader::new(file)
+                .lines()
+                .filter_map(|line| line.ok())
+                .filter_map(|line| serde_json::from_str::<Record>(&line).ok())
+                .filter(|record| record.ip == ip)
+                .collect()
+        })
+        .unwrap_or_default()
+}
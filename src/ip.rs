@@ -1,10 +1,51 @@
 extern crate toml;
 
 use std::path::{Path, PathBuf};
-use std::fs::{read_dir, remove_file, File, OpenOptions};
-use std::io::{Read, Write};
+use std::fs::{copy, read_dir, remove_file, rename, File, OpenOptions};
+use std::io::{self, Read, Write};
 use std::convert::Into;
-use errors::Result;
+use std::sync::{Mutex, MutexGuard};
+use errors::{Error, ErrorKind, Result};
+use probe;
+
+lazy_static! {
+    /// Held while files in the data directory are written or removed.
+    static ref STORE: Mutex<()> = Mutex::new(());
+}
+
+pub fn is_valid_domain(domain: &str) -> bool {
+    let domain = domain.trim_right_matches('.');
+    !domain.is_empty() && domain.len() <= 253 && domain.split('.').all(|label| {
+        !label.is_empty() && label.len() <= 63 && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    })
+}
+
+pub fn parse_port(s: &str) -> Option<u32> {
+    s.trim()
+        .parse::<u32>()
+        .ok()
+        .filter(|port| *port > 0 && *port <= 65_535)
+}
+
+pub fn parse_tags(s: &str) -> Vec<String> {
+    let mut tags: Vec<String> = vec![];
+    for tag in s.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|tag| !tag.is_empty())
+    {
+        if !tags.iter().any(|t| t == tag) {
+            tags.push(tag.to_owned());
+        }
+    }
+    tags
+}
+
+pub fn ip_key(ip: &str) -> Vec<u32> {
+    ip.split('.').map(|i| i.parse::<u32>().unwrap_or(0)).collect()
+}
 
 #[derive(Deserialize)]
 struct InnerEntry {
@@ -13,6 +54,16 @@ struct InnerEntry {
     using: bool,
     open_ports: Vec<u32>,
     #[serde(skip_serializing_if = "Option::is_none")] description: Option<String>,
+    #[serde(default)] last_probed: Option<u64>,
+    #[serde(default)] last_seen: Option<u64>,
+    #[serde(default)] reachable_ports: Vec<u32>,
+    #[serde(default)] scan_ports: Vec<u32>,
+    #[serde(default)] ignored_ports: Vec<u32>,
+    #[serde(default)] reported_ports: Vec<u32>,
+    #[serde(default)] dns_issues: Vec<String>,
+    #[serde(default)] owner: Option<String>,
+    #[serde(default)] updated: Option<u64>,
+    #[serde(default)] tags: Vec<String>,
 }
 
 impl InnerEntry {
@@ -23,18 +74,38 @@ impl InnerEntry {
             using: self.using,
             open_ports: self.open_ports,
             description: self.description,
+            last_probed: self.last_probed,
+            last_seen: self.last_seen,
+            reachable_ports: self.reachable_ports,
+            scan_ports: self.scan_ports,
+            ignored_ports: self.ignored_ports,
+            reported_ports: self.reported_ports,
+            dns_issues: self.dns_issues,
+            owner: self.owner,
+            updated: self.updated,
+            tags: self.tags,
             path,
         }
     }
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 pub struct Entry {
     pub ip: String,
     pub domain: Option<String>,
     pub using: bool,
     pub open_ports: Vec<u32>,
     pub description: Option<String>,
+    pub last_probed: Option<u64>,
+    pub last_seen: Option<u64>,
+    pub reachable_ports: Vec<u32>,
+    pub scan_ports: Vec<u32>,
+    pub ignored_ports: Vec<u32>,
+    pub reported_ports: Vec<u32>,
+    pub dns_issues: Vec<String>,
+    pub owner: Option<String>,
+    pub updated: Option<u64>,
+    pub tags: Vec<String>,
     #[serde(skip_serializing)] path: PathBuf,
 }
 
@@ -46,26 +117,42 @@ impl Into<InnerEntry> for Entry {
             using: self.using,
             open_ports: self.open_ports,
             description: self.description,
+            last_probed: self.last_probed,
+            last_seen: self.last_seen,
+            reachable_ports: self.reachable_ports,
+            scan_ports: self.scan_ports,
+            ignored_ports: self.ignored_ports,
+            reported_ports: self.reported_ports,
+            dns_issues: self.dns_issues,
+            owner: self.owner,
+            updated: self.updated,
+            tags: self.tags,
         }
     }
 }
 
 impl Entry {
-    pub fn new(ip: &str, data_path: &Path) -> Result<Entry> {
+    /// An entry which is not written until it is committed as a new one.
+    pub fn draft(ip: &str, data_path: &Path) -> Entry {
         let p = data_path.join(Path::new(&ip.replace(".", "-")).with_extension("toml"));
-        let mut file: File = File::create(&p)?;
-        let entry = Entry {
+        Entry {
             ip: ip.to_owned(),
             domain: None,
             using: false,
             open_ports: vec![],
             description: None,
+            last_probed: None,
+            last_seen: None,
+            reachable_ports: vec![],
+            scan_ports: vec![],
+            ignored_ports: vec![],
+            reported_ports: vec![],
+            dns_issues: vec![],
+            owner: None,
+            updated: Some(probe::now()),
+            tags: vec![],
             path: p,
-        };
-        let s = toml::to_string_pretty(&entry)?;
-        file.write_all(s.as_bytes())?;
-
-        Ok(entry)
+        }
     }
 
     pub fn from_ip(ip: &str, data_path: &Path) -> Option<Entry> {
@@ -83,16 +170,25 @@ impl Entry {
             .map(|ie| ie.into_entry(p))
     }
 
-    pub fn delete(&self) -> Result<()> {
-        remove_file(&self.path)?;
-        Ok(())
+    pub fn touch(&mut self) {
+        self.updated = Some(probe::now());
     }
 
+    pub fn add_ports(&mut self, ports: &[u32]) {
+        for port in ports {
+            if !self.open_ports.contains(port) {
+                self.open_ports.push(*port);
+            }
+        }
+    }
+
+    /// Fails if the entry has been deleted in the meantime.
     pub fn save(&self) -> Result<()> {
-        let mut file: File = OpenOptions::new()
-            .write(true)
-            .truncate(true)
-            .open(&self.path)?;
+        commit(&[(Some(self), Some(self))])
+    }
+
+    fn write(&self, path: &Path) -> Result<()> {
+        let mut file: File = File::create(path)?;
         let s = toml::to_string_pretty(&self)?;
         file.write_all(s.as_bytes())?;
 
@@ -104,6 +200,9 @@ impl Entry {
             .map(|dir_entries| {
                 dir_entries
                     .filter_map(|dir_entry| dir_entry.ok())
+                    .filter(|dir_entry| {
+                        dir_entry.path().extension().and_then(|ext| ext.to_str()) == Some("toml")
+                    })
                     .filter_map(|dir_entry| {
                         File::open(dir_entry.path()).ok().and_then(|mut file| {
                             let mut content = String::new();
@@ -116,21 +215,7 @@ impl Entry {
                     .collect()
             })
             .unwrap_or_default();
-        v.sort_unstable_by(|a, b| {
-            let aa = a.ip
-                .split('.')
-                .map(|i| i.parse::<u32>().unwrap())
-                .collect::<Vec<_>>();
-            let bb = b.ip
-                .split('.')
-                .map(|i| i.parse::<u32>().unwrap())
-                .collect::<Vec<_>>();
-            aa[0]
-                .cmp(&bb[0])
-                .then_with(|| aa[1].cmp(&bb[1]))
-                .then_with(|| aa[2].cmp(&bb[2]))
-                .then_with(|| aa[3].cmp(&bb[3]))
-        });
+        v.sort_unstable_by_key(|entry| ip_key(&entry.ip));
         v
     }
 
@@ -145,6 +230,7 @@ impl Entry {
                             .as_ref()
                             .map(|s| s.contains(q))
                             .unwrap_or(false)
+                        || entry.tags.iter().any(|tag| tag == q)
                         || (entry.using && q == "사용중")
                         || (!entry.using && q == "미사용")
                         || entry.open_ports.contains(&q.parse::<u32>().unwrap_or(0))
@@ -158,3 +244,207 @@ impl Entry {
             .collect()
     }
 }
+
+/// `(before, after)`, where `before` is `None` for an entry to be created and `after` is `None`
+/// for one to be deleted.
+pub type Change<'a> = (Option<&'a Entry>, Option<&'a Entry>);
+
+/// Whether `commit` failed because an entry to be created is already there.
+pub fn is_already_exists(error: &Error) -> bool {
+    match *error.kind() {
+        ErrorKind::Io(ref e) => e.kind() == io::ErrorKind::AlreadyExists,
+        _ => false,
+    }
+}
+
+/// Holds the store so that whatever is read while it is held can't be changed by anyone else
+/// before it is committed.
+pub struct Store {
+    _guard: MutexGuard<'static, ()>,
+}
+
+pub fn lock() -> Store {
+    Store {
+        _guard: STORE.lock().unwrap_or_else(|e| e.into_inner()),
+    }
+}
+
+pub fn commit(changes: &[Change]) -> Result<()> {
+    lock().commit(changes)
+}
+
+impl Store {
+    /// Either all of `changes` reach the store or none of them do. Created entries must not
+    /// exist yet, and updated or deleted ones must still exist.
+    pub fn commit(&self, changes: &[Change]) -> Result<()> {
+        let mut journal = Journal::default();
+        let result = journal.apply(changes);
+        if result.is_err() {
+            journal.roll_back();
+        }
+        journal.clean_up();
+        result
+    }
+}
+
+/// What a commit has done so far, so that it can be undone.
+#[derive(Default)]
+struct Journal {
+    /// Files made for created entries.
+    created: Vec<PathBuf>,
+    temps: Vec<PathBuf>,
+    /// Copies of replaced or removed files, along with where they belong.
+    backups: Vec<(PathBuf, PathBuf)>,
+}
+
+impl Journal {
+    fn apply(&mut self, changes: &[Change]) -> Result<()> {
+        for &(before, after) in changes {
+            match (before, after) {
+                (None, Some(entry)) => {
+                    OpenOptions::new()
+                        .write(true)
+                        .create_new(true)
+                        .open(&entry.path)?;
+                    self.created.push(entry.path.clone());
+                }
+                (Some(entry), _) if !entry.path.exists() => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("{} has been deleted", entry.ip),
+                    ).into())
+                }
+                _ => (),
+            }
+        }
+        // Everything is written out before any stored file is touched.
+        for &(_, after) in changes {
+            if let Some(entry) = after {
+                let temp = entry.path.with_extension("tmp");
+                self.temps.push(temp.clone());
+                entry.write(&temp)?;
+            }
+        }
+        for &(before, after) in changes {
+            if let Some(entry) = before {
+                let backup = entry.path.with_extension("bak");
+                copy(&entry.path, &backup)?;
+                self.backups.push((backup, entry.path.clone()));
+            }
+            match after {
+                Some(entry) => rename(entry.path.with_extension("tmp"), &entry.path)?,
+                None => if let Some(entry) = before {
+                    remove_file(&entry.path)?;
+                },
+            }
+        }
+
+        Ok(())
+    }
+
+    fn roll_back(&self) {
+        for &(ref backup, ref path) in &self.backups {
+            let _ = rename(backup, path);
+        }
+        for path in &self.created {
+            let _ = remove_file(path);
+        }
+    }
+
+    fn clean_up(&self) {
+        for path in self.temps
+            .iter()
+            .chain(self.backups.iter().map(|&(ref backup, _)| backup))
+        {
+            let _ = remove_file(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs::{create_dir_all, remove_dir_all};
+
+    fn data_path(name: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("ip-manager-{}-{}", name, ::std::process::id()));
+        let _ = remove_dir_all(&path);
+        create_dir_all(&path).unwrap();
+        path
+    }
+
+    #[test]
+    fn save_does_not_bring_back_deleted_entries() {
+        let path = data_path("save");
+        let entry = Entry::draft("10.0.0.1", &path);
+        commit(&[(None, Some(&entry))]).unwrap();
+        commit(&[(Some(&entry), None)]).unwrap();
+        assert!(entry.save().is_err());
+        assert!(Entry::from_ip("10.0.0.1", &path).is_none());
+        remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn creating_an_existing_entry_fails() {
+        let path = data_path("create");
+        let mut first = Entry::draft("10.0.0.1", &path);
+        first.description = Some("first".to_owned());
+        commit(&[(None, Some(&first))]).unwrap();
+
+        let second = Entry::draft("10.0.0.1", &path);
+        let other = Entry::draft("10.0.0.2", &path);
+        let error = commit(&[(None, Some(&other)), (None, Some(&second))]).unwrap_err();
+        assert!(is_already_exists(&error));
+        let stored = Entry::from_ip("10.0.0.1", &path).unwrap();
+        assert_eq!(stored.description, first.description);
+        assert!(Entry::from_ip("10.0.0.2", &path).is_none());
+        remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn failed_commit_leaves_the_store_unchanged() {
+        let path = data_path("rollback");
+        let mut first = Entry::draft("10.0.0.1", &path);
+        first.description = Some("before".to_owned());
+        commit(&[(None, Some(&first))]).unwrap();
+        let deleted = Entry::draft("10.0.0.2", &path);
+        commit(&[(None, Some(&deleted))]).unwrap();
+        // A directory in place of the third entry fails the batch after the first two were
+        // already replaced and removed.
+        let broken = Entry::draft("10.0.0.3", &path);
+        create_dir_all(broken.path.join("x")).unwrap();
+
+        let mut changed = first.clone();
+        changed.description = Some("after".to_owned());
+        let created = Entry::draft("10.0.0.4", &path);
+        assert!(
+            commit(&[
+                (Some(&first), Some(&changed)),
+                (Some(&deleted), None),
+                (None, Some(&created)),
+                (Some(&broken), Some(&broken)),
+            ]).is_err()
+        );
+
+        assert_eq!(
+            Entry::from_ip("10.0.0.1", &path).unwrap().description,
+            first.description
+        );
+        assert!(Entry::from_ip("10.0.0.2", &path).is_some());
+        assert!(!created.path.exists());
+        let mut names = read_dir(&path)
+            .unwrap()
+            .map(|dir_entry| dir_entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, vec!["10-0-0-1.toml", "10-0-0-2.toml", "10-0-0-3.toml"]);
+        remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn parse_tags_splits_and_dedups() {
+        assert_eq!(parse_tags("gpu, lab  gpu,,web"), vec!["gpu", "lab", "web"]);
+        assert!(parse_tags(" , ").is_empty());
+    }
+}
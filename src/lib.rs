@@ -3,19 +3,41 @@ extern crate error_chain;
 #[macro_use]
 extern crate lazy_static;
 extern crate regex;
+extern crate serde_urlencoded;
 #[macro_use]
 extern crate serde_derive;
 #[macro_use]
 extern crate serde_json;
 
+use std::panic;
+
 mod errors;
 mod settings;
 mod slack;
 mod ip;
+mod export;
+mod probe;
+mod drift;
+mod subnet;
+mod discovery;
+mod dns;
+mod worker;
+mod unfurl;
+mod command;
+mod history;
+mod role;
+mod page;
+mod bulk;
+mod share;
 
 lazy_static! {
     static ref SETTINGS: settings::Settings =
         settings::Settings::try_new().unwrap();
+    static ref WORKERS: worker::Pool = worker::Pool::new(SETTINGS.workers());
+    /// Block actions get a lane of their own, since the `trigger_id` they open modals with
+    /// expires in three seconds.
+    static ref INTERACTIONS: worker::Pool = worker::Pool::new(SETTINGS.workers());
+    static ref UNFURLED: unfurl::Limiter = unfurl::Limiter::default();
 }
 
 pub enum Response {
@@ -23,189 +45,1234 @@ pub enum Response {
     Unauthorized,
     Empty,
     Json(String),
+    Text(String),
     Error,
 }
 
-pub fn handle_slash_command(body: &str) -> Response {
-    lazy_static! {
-        static ref REGEX_IP: regex::Regex =
-            regex::Regex::new(r"^\d{1,3}.\d{1,3}.\d{1,3}.\d{1,3}$")
-            .unwrap();
+pub fn spawn_background_jobs() {
+    if SETTINGS.roles().admins.is_empty() {
+        errors::log(
+            "roles",
+            &"no admins are configured; every user but read_only ones may edit any entry".into(),
+        );
+    }
+    if SETTINGS.probe().interval > 0 {
+        probe::spawn(SETTINGS.data_path().to_owned(), SETTINGS.probe().clone());
+    }
+    if SETTINGS.drift().interval > 0 && !SETTINGS.drift().channel.is_empty() {
+        drift::spawn(
+            SETTINGS.data_path().to_owned(),
+            SETTINGS.drift().clone(),
+            std::time::Duration::from_millis(SETTINGS.probe().timeout_ms),
+            SETTINGS.token().to_owned(),
+        );
+    }
+    if SETTINGS.discovery().interval > 0 && !SETTINGS.subnets().is_empty() {
+        discovery::spawn(
+            SETTINGS.subnets(),
+            SETTINGS.data_path().to_owned(),
+            SETTINGS.discovery().clone(),
+            SETTINGS.probe().clone(),
+            SETTINGS.token().to_owned(),
+        );
+    }
+    if SETTINGS.dns().interval > 0 {
+        match dns::Resolver::new(
+            SETTINGS.dns().resolver.as_ref().map(String::as_str),
+            std::time::Duration::from_millis(SETTINGS.dns().timeout_ms),
+        ) {
+            Ok(resolver) => dns::spawn(
+                SETTINGS.data_path().to_owned(),
+                SETTINGS.dns().clone(),
+                resolver,
+                SETTINGS.token().to_owned(),
+            ),
+            Err(e) => errors::log("DNS check disabled", &e),
+        }
+    }
+}
+
+pub fn sweep() -> Vec<String> {
+    discovery::sweep(
+        &SETTINGS.subnets(),
+        SETTINGS.data_path(),
+        SETTINGS.discovery(),
+        SETTINGS.probe(),
+    ).map(|found| found.into_iter().map(|c| c.ip).collect())
+        .unwrap_or_default()
+}
+
+pub enum Export {
+    Hosts,
+    SshConfig,
+}
+
+pub fn export(kind: &Export) -> String {
+    let entries = ip::Entry::list(SETTINGS.data_path());
+    match *kind {
+        Export::Hosts => export::generate_hosts(&entries),
+        Export::SshConfig => export::generate_ssh_config(&entries, SETTINGS.ssh_ports()),
+    }
+}
+
+pub fn handle_export(kind: &Export, query: &str) -> Response {
+    #[derive(Deserialize)]
+    struct Query {
+        token: String,
+    }
+
+    match serde_urlencoded::from_str::<Query>(query) {
+        Ok(ref q) if SETTINGS.verify_export(&q.token) => Response::Text(export(kind)),
+        _ => Response::Unauthorized,
+    }
+}
+
+pub use slack::signature::Signature;
+
+fn authorize(body: &str, signature: Option<&Signature>, token: &str) -> bool {
+    match (SETTINGS.signing_secret(), signature) {
+        (Some(secret), Some(signature)) => signature.verify(secret, body, probe::now()),
+        (Some(_), None) => SETTINGS.allow_token_fallback() && SETTINGS.verify(token),
+        (None, _) => SETTINGS.verify(token),
+    }
+}
+
+fn respond_later<F>(response_url: String, replace_original: bool, job: F)
+where
+    F: FnOnce() -> Response + Send + 'static,
+{
+    respond_on(&WORKERS, response_url, replace_original, job)
+}
+
+fn respond_on<F>(pool: &worker::Pool, response_url: String, replace_original: bool, job: F)
+where
+    F: FnOnce() -> Response + Send + 'static,
+{
+    pool.execute(move || {
+        let resp = panic::catch_unwind(panic::AssertUnwindSafe(job)).unwrap_or(Response::Error);
+        let result = match resp {
+            Response::Json(message) => {
+                slack::respond(&response_url, &message, replace_original)
+            }
+            Response::Empty => Ok(()),
+            _ => slack::respond(
+                &response_url,
+                &slack::message::generate_error_message(),
+                false,
+            ),
+        };
+        if let Err(e) = result {
+            errors::log(&format!("failed to respond to {}", response_url), &e);
+        }
+    });
+}
+
+fn apply_change(
+    before: Option<&ip::Entry>,
+    after: Option<&ip::Entry>,
+    user_id: &str,
+) -> errors::Result<()> {
+    apply_changes(&ip::lock(), &[(before, after)], user_id)
+}
+
+/// Either all of `changes` reach the store or none of them do.
+fn apply_changes(store: &ip::Store, changes: &[ip::Change], user_id: &str) -> errors::Result<()> {
+    store.commit(changes)?;
+    let mut records = vec![];
+    for &(before, after) in changes {
+        let (ip, action) = match (before, after) {
+            (None, Some(after)) => (&after.ip, "create"),
+            (Some(before), None) => (&before.ip, "delete"),
+            (Some(_), Some(after)) => (&after.ip, "update"),
+            (None, None) => continue,
+        };
+        let changes = history::diff(before, after);
+        if action == "update" && changes.is_empty() {
+            continue;
+        }
+        let record = history::Record {
+            time: probe::now(),
+            ip: ip.clone(),
+            user: user_id.to_owned(),
+            action: action.to_owned(),
+            changes,
+            attempted: None,
+        };
+        if let Err(e) = history::record(SETTINGS.history_path(), &record) {
+            errors::log(&format!("failed to record history of {}", ip), &e);
+        }
+        records.push(record);
+    }
+    notify(records);
+    Ok(())
+}
+
+/// Changes committed together are notified in one message.
+fn notify(records: Vec<history::Record>) {
+    let settings = SETTINGS.notify();
+    if settings.channel.is_empty() {
+        return;
+    }
+    let records = records
+        .into_iter()
+        .filter(|record| {
+            let event = match record.action.as_str() {
+                "update" if record.changes.len() == 1 && record.changes[0].field == "using" => {
+                    "toggle"
+                }
+                "update" => "edit",
+                action => action,
+            };
+            settings.events.iter().any(|e| e == event)
+        })
+        .collect::<Vec<_>>();
+    let message = match records.len() {
+        0 => return,
+        1 => slack::message::generate_change_message(&records[0]),
+        _ => slack::message::generate_changes_message(&records),
+    };
+    WORKERS.execute(move || {
+        if let Err(e) = slack::post_message(
+            &SETTINGS.notify().channel,
+            &message,
+            SETTINGS.token(),
+        ) {
+            errors::log(&format!("failed to notify change of {}", records[0].ip), &e);
+        }
+    });
+}
+
+fn permitted(user_id: &str, entry: Option<&ip::Entry>) -> bool {
+    role::permitted(user_id, entry, SETTINGS.roles(), SETTINGS.token())
+}
+
+fn record_denied(user_id: &str, target: &str, attempted: &str) {
+    let record = history::Record {
+        time: probe::now(),
+        ip: target.to_owned(),
+        user: user_id.to_owned(),
+        action: "denied".to_owned(),
+        changes: vec![],
+        attempted: Some(attempted.to_owned()),
+    };
+    if let Err(e) = history::record(SETTINGS.history_path(), &record) {
+        errors::log(&format!("failed to record denied {} on {}", attempted, target), &e);
+    }
+}
+
+fn deny_action(
+    user_id: &str,
+    target: &str,
+    attempted: &str,
+    response_url: Option<&str>,
+) -> Response {
+    record_denied(user_id, target, attempted);
+    let message = slack::message::generate_denied_message();
+    let result = match response_url {
+        Some(response_url) => slack::respond(response_url, &message, false),
+        None => slack::post_message(user_id, &message, SETTINGS.token()),
+    };
+    if let Err(e) = result {
+        errors::log(&format!("failed to deny {} to {}", attempted, user_id), &e);
+    }
+    Response::Empty
+}
+
+fn subnet_usage(subnets: &[subnet::Subnet], entries: &[ip::Entry]) -> Vec<slack::home::Usage> {
+    subnets
+        .iter()
+        .map(|subnet| {
+            let registered = entries
+                .iter()
+                .filter(|entry| subnet.contains(&entry.ip))
+                .collect::<Vec<_>>();
+            slack::home::Usage {
+                subnet: subnet.to_string(),
+                using: registered.iter().filter(|entry| entry.using).count(),
+                registered: registered.len(),
+                size: subnet.size(),
+            }
+        })
+        .collect()
+}
+
+fn page_entries(page: &page::Page, subnets: &[subnet::Subnet]) -> Vec<ip::Entry> {
+    let mut entries = match page.query {
+        Some(ref query) => ip::Entry::search(query, SETTINGS.data_path()),
+        None => ip::Entry::list(SETTINGS.data_path()),
+    };
+    page::arrange(&mut entries, &page.options, subnets);
+    entries
+}
+
+fn generate_page(page: &page::Page) -> String {
+    let subnets = SETTINGS.subnets();
+    slack::message::generate_list_message(
+        &page_entries(page, &subnets),
+        page,
+        &subnets,
+        page.options.size(SETTINGS.page_size()),
+    )
+}
+
+fn dispatch_command(text: &str, user_id: &str) -> Response {
+    use command::Command;
+
+    let command = match command::parse(text) {
+        Ok(command) => command,
+        Err(name) => {
+            return Response::Json(slack::message::generate_usage_error_message(name))
+        }
+    };
+    Response::Json(match command {
+        Command::Help(topic) => {
+            slack::message::generate_help_message(topic.as_ref().map(String::as_str))
+        }
+        Command::List(options) => generate_page(&page::Page::new(None, options)),
+        Command::Show(ip) => ip::Entry::from_ip(&ip, SETTINGS.data_path())
+            .map(|entry| slack::message::generate_ip_message(&entry))
+            .unwrap_or_else(|| slack::message::generate_create_new_message(&ip)),
+        Command::Search(query, options) => {
+            generate_page(&page::Page::new(Some(query), options))
+        }
+        Command::Add(ip, description) => {
+            if !permitted(user_id, None) {
+                record_denied(user_id, &ip, "add");
+                return Response::Json(slack::message::generate_denied_message());
+            }
+            let mut entry = ip::Entry::draft(&ip, SETTINGS.data_path());
+            entry.description = description;
+            entry.owner = Some(user_id.to_owned());
+            match apply_change(None, Some(&entry), user_id) {
+                Ok(()) => slack::message::generate_ip_message(&entry),
+                Err(ref e) if ip::is_already_exists(e) => {
+                    slack::message::generate_already_exists_message(&ip)
+                }
+                Err(_) => return Response::Error,
+            }
+        }
+        Command::Release(ip) => match ip::Entry::from_ip(&ip, SETTINGS.data_path()) {
+            Some(ref entry) if !permitted(user_id, Some(entry)) => {
+                record_denied(user_id, &ip, "release");
+                slack::message::generate_denied_message()
+            }
+            Some(mut entry) => {
+                let before = entry.clone();
+                entry.using = false;
+                entry.owner = None;
+                entry.touch();
+                if apply_change(Some(&before), Some(&entry), user_id).is_err() {
+                    return Response::Error;
+                }
+                slack::message::generate_ip_message(&entry)
+            }
+            None => slack::message::generate_inexist_message(),
+        },
+        Command::History(ip) => slack::message::generate_history_message(
+            &ip,
+            &history::load(SETTINGS.history_path(), &ip),
+        ),
+        Command::Subnet(None) => slack::message::generate_subnet_message(
+            &subnet_usage(&SETTINGS.subnets(), &ip::Entry::list(SETTINGS.data_path())),
+            &[],
+        ),
+        Command::Subnet(Some(subnet)) => match subnet::Subnet::from_str(&subnet) {
+            Ok(subnet) => {
+                let entries = ip::Entry::list(SETTINGS.data_path());
+                let free = subnet
+                    .hosts()
+                    .filter(|host| !entries.iter().any(|entry| &entry.ip == host))
+                    .take(20)
+                    .collect::<Vec<_>>();
+                slack::message::generate_subnet_message(
+                    &subnet_usage(&[subnet], &entries),
+                    &free,
+                )
+            }
+            Err(_) => slack::message::generate_usage_error_message("subnet"),
+        },
+        Command::Export(kind) => slack::message::generate_export_message(&export(&kind)),
+    })
+}
+
+/// The share button posts the message as it was rendered here, without running the
+/// command again.
+fn with_visibility(message: &str, share: bool) -> String {
+    let key = if share {
+        String::new()
+    } else {
+        share::keep(&slack::message::generate_shared_message(message))
+    };
+    slack::message::with_visibility(message, share, &key)
+}
+
+pub fn handle_slash_command(body: &str, signature: Option<&Signature>) -> Response {
+    match slack::slash_command::Request::from_str(body) {
+        Ok(ref command) if !authorize(body, signature, &command.token) => Response::Unauthorized,
+        Ok(command) => {
+            let (text, user_id) = (command.text, command.user_id);
+            respond_later(command.response_url, false, move || {
+                let (text, share) = command::take_flag(&text, "--share");
+                match dispatch_command(&text, &user_id) {
+                    Response::Json(message) => Response::Json(
+                        match command::parse(&text) {
+                            Ok(ref command) if command.shareable() => {
+                                with_visibility(&message, share || SETTINGS.share_by_default())
+                            }
+                            _ => message,
+                        },
+                    ),
+                    response => response,
+                }
+            });
+            Response::Empty
+        }
+        Err(_) => Response::Error,
+    }
+}
+
+fn saved(result: errors::Result<()>, message: String) -> Response {
+    match result {
+        Ok(()) => Response::Json(message),
+        Err(e) => {
+            errors::log("failed to save", &e);
+            Response::Error
+        }
+    }
+}
+
+fn shown(result: errors::Result<()>) -> Response {
+    match result {
+        Ok(()) => Response::Empty,
+        Err(e) => {
+            errors::log("failed to open modal", &e);
+            Response::Error
+        }
+    }
+}
+
+fn handle_action(
+    callback: &slack::callback::Action,
+    name: &str,
+    value: &str,
+    user_id: &str,
+    trigger_id: &str,
+    response_url: Option<&str>,
+) -> Response {
+    use slack::callback::Action;
+
+    match *callback {
+        Action::Ip { ref ip } => ip::Entry::from_ip(ip, SETTINGS.data_path())
+            .map(|mut entry| {
+                if name != "refresh" && !permitted(user_id, Some(&entry)) {
+                    return deny_action(user_id, &entry.ip, name, response_url);
+                }
+                match name {
+                    "edit_domain" => shown(slack::modal::show_edit_domain_modal(
+                        &entry,
+                        trigger_id,
+                        response_url,
+                        SETTINGS.token(),
+                    )),
+                    "toggle_using" => {
+                        let before = entry.clone();
+                        entry.using = !entry.using;
+                        entry.touch();
+                        saved(
+                            apply_change(Some(&before), Some(&entry), user_id),
+                            slack::message::generate_ip_message(&entry),
+                        )
+                    }
+                    "edit_port" => shown(slack::modal::show_edit_port_modal(
+                        &entry.ip,
+                        value,
+                        trigger_id,
+                        response_url,
+                        SETTINGS.token(),
+                    )),
+                    "add_port" => shown(slack::modal::show_add_port_modal(
+                        &entry.ip,
+                        trigger_id,
+                        response_url,
+                        SETTINGS.token(),
+                    )),
+                    "edit_description" => shown(slack::modal::show_edit_description_modal(
+                        &entry,
+                        trigger_id,
+                        response_url,
+                        SETTINGS.token(),
+                    )),
+                    "edit_entry" => shown(slack::modal::show_edit_entry_modal(
+                        &entry,
+                        trigger_id,
+                        response_url,
+                        SETTINGS.token(),
+                    )),
+                    "refresh" => {
+                        Response::Json(slack::message::generate_ip_message(&entry))
+                    }
+                    "delete_entry" => saved(
+                        apply_change(Some(&entry), None, user_id),
+                        slack::message::generate_deleted_message(),
+                    ),
+                    _ => Response::Unimplemented,
+                }
+            })
+            .unwrap_or_else(|| {
+                Response::Json(slack::message::generate_inexist_message())
+            }),
+        Action::Drift { ref ip } => ip::Entry::from_ip(ip, SETTINGS.data_path())
+            .map(|mut entry| {
+                if !permitted(user_id, Some(&entry)) {
+                    return deny_action(user_id, &entry.ip, name, response_url);
+                }
+                if let Ok(port) = value.parse::<u32>() {
+                    let before = entry.clone();
+                    match name {
+                        "drift_add" => entry.add_ports(&[port]),
+                        "drift_ignore" => if !entry.ignored_ports.contains(&port) {
+                            entry.ignored_ports.push(port);
+                        },
+                        _ => return Response::Unimplemented,
+                    }
+                    entry.reported_ports.retain(|p| *p != port);
+                    if apply_change(Some(&before), Some(&entry), user_id).is_err() {
+                        return Response::Error;
+                    }
+                }
+                Response::Json(slack::message::generate_drift_message(
+                    &entry.ip,
+                    &drift::pending(&entry),
+                ))
+            })
+            .unwrap_or_else(|| {
+                Response::Json(slack::message::generate_inexist_message())
+            }),
+        Action::List | Action::Query => {
+            let page = match page::Page::from_str(value) {
+                Some(page) => page,
+                None => return Response::Json(slack::message::generate_expired_message()),
+            };
+            if name != "bulk" {
+                return Response::Json(generate_page(&page));
+            }
+            if !permitted(user_id, None) {
+                return deny_action(user_id, "", name, response_url);
+            }
+            shown(slack::modal::show_bulk_modal(
+                &page_entries(&page, &SETTINGS.subnets()),
+                &page,
+                trigger_id,
+                response_url,
+                SETTINGS.token(),
+            ))
+        }
+        Action::Open => {
+            let message = ip::Entry::from_ip(value, SETTINGS.data_path())
+                .map(|entry| slack::message::generate_ip_message(&entry))
+                .unwrap_or_else(slack::message::generate_inexist_message);
+            if response_url.is_some() {
+                return Response::Json(message);
+            }
+            // Selects on the home tab have nowhere to reply, so the card goes to a DM.
+            if let Err(e) = slack::post_message(user_id, &message, SETTINGS.token()) {
+                errors::log(&format!("failed to send {} to {}", value, user_id), &e);
+            }
+            Response::Empty
+        }
+        Action::CreateNew { ref ip } => {
+            if value != "create_new_entry" {
+                return Response::Json(slack::message::generate_cancelled_message());
+            }
+            if !permitted(user_id, None) {
+                return deny_action(user_id, ip, value, response_url);
+            }
+            let mut entry = ip::Entry::draft(ip, SETTINGS.data_path());
+            entry.owner = Some(user_id.to_owned());
+            match apply_change(None, Some(&entry), user_id) {
+                Err(ref e) if ip::is_already_exists(e) => Response::Json(
+                    slack::message::generate_already_exists_message(ip),
+                ),
+                result => saved(result, slack::message::generate_ip_message(&entry)),
+            }
+        }
+        Action::Share { ref key } => Response::Json(
+            share::get(key).unwrap_or_else(slack::message::generate_expired_message),
+        ),
+        Action::Home => {
+            if name != "search" && !permitted(user_id, None) {
+                return deny_action(user_id, "", name, response_url);
+            }
+            shown(match name {
+                "search" => slack::modal::show_search_modal(trigger_id, SETTINGS.token()),
+                "register" => show_register_modal(user_id, trigger_id),
+                "allocate" => slack::modal::show_allocate_modal(
+                    &SETTINGS
+                        .subnets()
+                        .iter()
+                        .map(|subnet| subnet.to_string())
+                        .collect::<Vec<_>>(),
+                    trigger_id,
+                    SETTINGS.token(),
+                ),
+                "transfer" => slack::modal::show_transfer_modal(trigger_id, SETTINGS.token()),
+                _ => return Response::Unimplemented,
+            })
+        }
+    }
+}
+
+fn publish_home(user_id: &str) {
+    let entries = ip::Entry::list(SETTINGS.data_path());
+    let own = entries
+        .iter()
+        .filter(|entry| entry.owner.as_ref().map(String::as_str) == Some(user_id))
+        .cloned()
+        .collect::<Vec<_>>();
+    let mut recent = entries
+        .iter()
+        .filter(|entry| entry.updated.is_some())
+        .cloned()
+        .collect::<Vec<_>>();
+    recent.sort_by(|a, b| b.updated.cmp(&a.updated));
+    recent.truncate(10);
+    let usage = subnet_usage(&SETTINGS.subnets(), &entries);
+    let view = slack::home::generate_home_view(&own, &recent, &usage);
+    if let Err(e) = slack::home::publish(user_id, &view, SETTINGS.token()) {
+        errors::log(&format!("failed to publish home of {}", user_id), &e);
+    }
+}
+
+/// `block_id` is the modal input which shows the error if the IP was registered meanwhile.
+fn register(
+    ip: &str,
+    description: Option<&str>,
+    using: bool,
+    user_id: &str,
+    block_id: &str,
+) -> Response {
+    let mut entry = ip::Entry::draft(ip, SETTINGS.data_path());
+    entry.description = description.map(str::to_owned);
+    entry.using = using;
+    entry.owner = Some(user_id.to_owned());
+    match apply_change(None, Some(&entry), user_id) {
+        Ok(()) => {
+            send_card(entry, user_id);
+            Response::Empty
+        }
+        Err(ref e) if ip::is_already_exists(e) => Response::Json(
+            slack::modal::generate_errors(&[
+                (block_id.to_owned(), "이미 등록된 IP입니다.".to_owned()),
+            ]),
+        ),
+        Err(_) => Response::Error,
+    }
+}
+
+/// DMs the card of a newly registered entry and refreshes the home tab.
+fn send_card(entry: ip::Entry, user_id: &str) {
+    let user_id = user_id.to_owned();
+    WORKERS.execute(move || {
+        if let Err(e) = slack::post_message(
+            &user_id,
+            &slack::message::generate_ip_message(&entry),
+            SETTINGS.token(),
+        ) {
+            errors::log(&format!("failed to send {} to {}", entry.ip, user_id), &e);
+        }
+        publish_home(&user_id);
+    });
+}
+
+fn handle_home_submission(
+    submission: &slack::submission::ViewSubmission,
+    callback: &slack::callback::View,
+) -> Response {
+    use slack::callback::View;
+
+    let user_id = &submission.user.id;
+    let description = submission.value("description");
+    let name = match *callback {
+        View::Search => "search",
+        View::Allocate => "allocate",
+        View::Transfer => "transfer",
+        _ => return Response::Unimplemented,
+    };
+    if name != "search" && !permitted(user_id, None) {
+        record_denied(user_id, submission.value("ip").unwrap_or_default(), name);
+        return Response::Json(slack::modal::generate_denied());
+    }
+    match name {
+        "search" => {
+            let query = submission.value("query").unwrap_or_default();
+            Response::Json(slack::modal::generate_search_result(
+                query,
+                &ip::Entry::search(query, SETTINGS.data_path()),
+            ))
+        }
+        "allocate" => if let Some(ip) = submission.selected_option("ip") {
+            let before = match ip::Entry::from_ip(ip, SETTINGS.data_path()) {
+                Some(ref entry) if !entry.using => entry.clone(),
+                _ => {
+                    return Response::Json(slack::modal::generate_errors(&[
+                        ("ip".to_owned(), "이미 사용중인 IP입니다.".to_owned()),
+                    ]))
+                }
+            };
+            let mut entry = before.clone();
+            entry.using = true;
+            entry.owner = Some(user_id.clone());
+            if description.is_some() {
+                entry.description = description.map(str::to_owned);
+            }
+            entry.touch();
+            if apply_change(Some(&before), Some(&entry), user_id).is_err() {
+                return Response::Error;
+            }
+            send_card(entry, user_id);
+            Response::Empty
+        } else {
+            let subnet = submission
+                .selected_option("subnet")
+                .and_then(|subnet| subnet::Subnet::from_str(subnet).ok());
+            let candidates = discovery::load(&SETTINGS.discovery().path);
+            let free = subnet.and_then(|subnet| {
+                subnet.hosts().find(|host| {
+                    ip::Entry::from_ip(host, SETTINGS.data_path()).is_none()
+                        && !candidates.iter().any(|c| &c.ip == host)
+                })
+            });
+            match free {
+                Some(ip) => register(&ip, description, true, user_id, "subnet"),
+                None if SETTINGS.subnets().is_empty() => {
+                    Response::Json(slack::modal::generate_errors(&[
+                        ("ip".to_owned(), "할당할 IP를 선택해 주세요.".to_owned()),
+                    ]))
+                }
+                None => Response::Json(slack::modal::generate_errors(&[
+                    ("subnet".to_owned(), "할당할 수 있는 IP가 없습니다.".to_owned()),
+                ])),
+            }
+        },
+        "transfer" => {
+            let ip = submission.selected_option("ip").unwrap_or_default();
+            let before = match ip::Entry::from_ip(ip, SETTINGS.data_path()) {
+                Some(entry) => entry,
+                None => return Response::Json(slack::modal::generate_expired()),
+            };
+            if !permitted(user_id, Some(&before)) {
+                record_denied(user_id, ip, name);
+                return Response::Json(slack::modal::generate_denied());
+            }
+            let operation = bulk::Operation::Owner {
+                owner: submission.selected_user("owner").unwrap_or_default().to_owned(),
+            };
+            match operation.apply(&before) {
+                Some(ref entry) if apply_change(Some(&before), Some(entry), user_id).is_ok() => {
+                    Response::Empty
+                }
+                _ => Response::Error,
+            }
+        }
+        _ => Response::Unimplemented,
+    }
+}
+
+/// The selection is confirmed in a second modal before anything is changed.
+fn handle_bulk_submission(
+    submission: &slack::submission::ViewSubmission,
+    callback: &slack::callback::View,
+) -> Response {
+    use slack::callback::View;
+
+    let user_id = &submission.user.id;
+    let mut metadata = slack::modal::Metadata::from_str(&submission.view.private_metadata);
+    let bulk = match *callback {
+        View::Bulk => {
+            let ips = (0..10)
+                .flat_map(|i| submission.selected(&format!("entries_{}", i)))
+                .map(str::to_owned)
+                .collect::<Vec<_>>();
+            if ips.is_empty() {
+                return Response::Json(slack::modal::generate_errors(&[
+                    ("entries_0".to_owned(), "IP를 선택해 주세요.".to_owned()),
+                ]));
+            }
+            let operation = match bulk::Operation::new(
+                submission.selected_option("operation").unwrap_or_default(),
+                submission.value("tags"),
+                submission.selected_user("owner"),
+            ) {
+                Ok(operation) => operation,
+                Err((block_id, message)) => {
+                    return Response::Json(slack::modal::generate_errors(&[
+                        (block_id.to_owned(), message.to_owned()),
+                    ]))
+                }
+            };
+            metadata.bulk = Some(bulk::Bulk {
+                ips,
+                operation,
+                gone: vec![],
+            });
+            return Response::Json(slack::modal::generate_bulk_confirm(&metadata));
+        }
+        _ => match metadata.bulk.take() {
+            Some(bulk) => bulk,
+            None => return Response::Json(slack::modal::generate_expired()),
+        },
+    };
+    // Looked up first, since it may ask Slack for usergroup members.
+    let role = role::role(user_id, SETTINGS.roles(), SETTINGS.token());
+    // Held until the changes are committed, so that nothing read here goes stale.
+    let store = ip::lock();
+    let (mut entries, mut gone) = (vec![], vec![]);
+    for ip in &bulk.ips {
+        match ip::Entry::from_ip(ip, SETTINGS.data_path()) {
+            Some(entry) => entries.push(entry),
+            None => gone.push(ip.clone()),
+        }
+    }
+    if !gone.is_empty() {
+        if entries.is_empty() {
+            return Response::Json(slack::modal::generate_inexist());
+        }
+        // The user confirms again what is left of the selection.
+        metadata.bulk = Some(bulk::Bulk {
+            ips: entries.iter().map(|entry| entry.ip.clone()).collect(),
+            gone,
+            operation: bulk.operation,
+        });
+        return Response::Json(slack::modal::generate_bulk_confirm(&metadata));
+    }
+    let denied = entries
+        .iter()
+        .filter(|entry| !role::allows(&role, user_id, Some(entry), SETTINGS.roles()))
+        .collect::<Vec<_>>();
+    if !denied.is_empty() {
+        let attempted = format!("bulk_{}", bulk.operation.name());
+        for entry in denied {
+            record_denied(user_id, &entry.ip, &attempted);
+        }
+        return Response::Json(slack::modal::generate_denied());
+    }
+    let after = entries
+        .iter()
+        .map(|entry| bulk.operation.apply(entry))
+        .collect::<Vec<_>>();
+    let changes = entries
+        .iter()
+        .zip(&after)
+        .map(|(before, after)| (Some(before), after.as_ref()))
+        .collect::<Vec<_>>();
+    if let Err(e) = apply_changes(&store, &changes, user_id) {
+        errors::log(&format!("failed to apply bulk {}", bulk.operation.name()), &e);
+        return Response::Error;
+    }
+    if let (Some(response_url), Some(page)) = (metadata.response_url, metadata.page) {
+        respond_later(response_url, true, move || {
+            Response::Json(generate_page(&page))
+        });
+    }
+    Response::Empty
+}
+
+fn parse_port_list(
+    submission: &slack::submission::ViewSubmission,
+    block_id: &str,
+    errors: &mut Vec<(String, String)>,
+) -> Vec<u32> {
+    let mut ports = vec![];
+    for p in submission
+        .value(block_id)
+        .unwrap_or_default()
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|p| !p.is_empty())
+    {
+        match ip::parse_port(p) {
+            Some(port) => if !ports.contains(&port) {
+                ports.push(port);
+            },
+            None => errors.push((
+                block_id.to_owned(),
+                format!("{}은(는) 올바른 포트가 아닙니다.", p),
+            )),
+        }
+    }
+    ports
+}
+
+fn handle_view_submission(submission: &slack::submission::ViewSubmission) -> Response {
+    use slack::callback::View;
+
+    let callback = match slack::callback::decode::<View>(&submission.view.callback_id) {
+        Some(callback) => callback,
+        None => return Response::Json(slack::modal::generate_expired()),
+    };
+    let (typ, ip) = match callback {
+        View::EditDomain { ref ip } => ("edit_domain", ip.as_str()),
+        View::EditDescription { ref ip } => ("edit_description", ip.as_str()),
+        View::EditPort { ref ip } => ("edit_port", ip.as_str()),
+        View::AddPort { ref ip } => ("add_port", ip.as_str()),
+        View::EditEntry { ref ip } => ("edit_entry", ip.as_str()),
+        View::RegisterEntry => ("register_entry", submission.value("ip").unwrap_or_default()),
+        View::Bulk | View::BulkConfirm => return handle_bulk_submission(submission, &callback),
+        _ => return handle_home_submission(submission, &callback),
+    };
+    let before = ip::Entry::from_ip(ip, SETTINGS.data_path());
+    let mut entry = match (typ, before.clone()) {
+        ("register_entry", Some(_)) => {
+            return Response::Json(slack::modal::generate_errors(&[
+                ("ip".to_owned(), "이미 등록된 IP입니다.".to_owned()),
+            ]))
+        }
+        ("register_entry", None) if !command::is_ip(ip) => {
+            return Response::Json(slack::modal::generate_errors(&[
+                ("ip".to_owned(), "올바른 IP가 아닙니다.".to_owned()),
+            ]))
+        }
+        ("register_entry", None) => ip::Entry::draft(ip, SETTINGS.data_path()),
+        (_, Some(entry)) => entry,
+        (_, None) => return Response::Json(slack::modal::generate_inexist()),
+    };
+    if !permitted(&submission.user.id, before.as_ref()) {
+        record_denied(&submission.user.id, ip, typ);
+        return Response::Json(slack::modal::generate_denied());
+    }
+    // Registering fills in the same fields as a full edit.
+    let full = typ == "edit_entry" || typ == "register_entry";
+    let metadata = slack::modal::Metadata::from_str(&submission.view.private_metadata);
+    let mut errors = vec![];
+
+    if typ == "edit_domain" || full {
+        match submission.value("domain") {
+            Some(domain) if !ip::is_valid_domain(domain) => errors.push((
+                "domain".to_owned(),
+                "올바른 도메인이 아닙니다.".to_owned(),
+            )),
+            domain => {
+                entry.domain = domain.map(str::to_owned);
+                entry.dns_issues.clear();
+            }
+        }
+    }
+    if typ == "edit_description" || full {
+        entry.description = submission.value("description").map(str::to_owned);
+    }
+    match typ {
+        "edit_port" => {
+            let old = metadata.port;
+            if let Some(i) = entry.open_ports.iter().position(|p| Some(*p) == old) {
+                match submission.value("port").map(|p| (p, ip::parse_port(p))) {
+                    Some((_, Some(port))) => entry.open_ports[i] = port,
+                    Some((p, None)) => errors.push((
+                        "port".to_owned(),
+                        format!("{}은(는) 올바른 포트가 아닙니다.", p),
+                    )),
+                    None => {
+                        entry.open_ports.remove(i);
+                    }
+                }
+            }
+        }
+        "add_port" => {
+            let mut ports = vec![];
+            for i in 1..5 {
+                let block_id = format!("new_port_{}", i);
+                if let Some(p) = submission.value(&block_id) {
+                    match ip::parse_port(p) {
+                        Some(port) => ports.push(port),
+                        None => errors.push((
+                            block_id,
+                            format!("{}은(는) 올바른 포트가 아닙니다.", p),
+                        )),
+                    }
+                }
+            }
+            entry.add_ports(&ports);
+        }
+        _ if full => {
+            entry.open_ports = parse_port_list(submission, "ports", &mut errors);
+            entry.scan_ports = parse_port_list(submission, "scan_ports", &mut errors);
+            entry.using = submission.selected("using").contains(&"using");
+            entry.owner = submission.selected_user("owner").map(str::to_owned);
+            entry.tags = ip::parse_tags(submission.value("tags").unwrap_or_default());
+        }
+        _ => (),
+    }
+
+    entry.touch();
+    if !errors.is_empty() {
+        return Response::Json(slack::modal::generate_errors(&errors));
     }
+    match apply_change(before.as_ref(), Some(&entry), &submission.user.id) {
+        Ok(()) => {
+            if let Some(response_url) = metadata.response_url {
+                respond_later(response_url, true, move || {
+                    Response::Json(slack::message::generate_ip_message(&entry))
+                });
+            } else if before.is_none() {
+                send_card(entry, &submission.user.id);
+            }
+            Response::Empty
+        }
+        // Another registration of the same IP got in first.
+        Err(ref e) if ip::is_already_exists(e) => {
+            Response::Json(slack::modal::generate_errors(&[
+                ("ip".to_owned(), "이미 등록된 IP입니다.".to_owned()),
+            ]))
+        }
+        Err(_) => Response::Error,
+    }
+}
 
-    slack::slash_command::Request::from_str(body)
+pub fn handle_submission(body: &str, signature: Option<&Signature>) -> Response {
+    slack::submission::Submission::from_str(body)
         .ok()
-        .and_then(|command| {
-            if !SETTINGS.verify(&command.token) {
-                Some(Response::Unauthorized)
-            } else if command.text.is_empty() {
-                Some(Response::Json(slack::message::generate_list_message(
-                    &ip::Entry::list(SETTINGS.data_path()),
-                    0,
-                )))
-            } else if REGEX_IP.is_match(&command.text) {
-                REGEX_IP
-                    .find(&command.text)
-                    .map(|m| m.as_str().to_owned())
-                    .map(|sip| {
-                        ip::Entry::from_ip(&sip, SETTINGS.data_path())
-                            .map(|entry| slack::message::generate_ip_message(&entry))
-                            .unwrap_or_else(|| slack::message::generate_create_new_message(&sip))
-                    })
-                    .map(Response::Json)
-            } else {
-                Some(Response::Json(slack::message::generate_query_message(
-                    &command.text,
-                    &ip::Entry::search(&command.text, SETTINGS.data_path()),
-                    0,
-                )))
+        .map(|submission| {
+            use slack::submission::Submission;
+            if !authorize(body, signature, submission.token()) {
+                return Response::Unauthorized;
+            }
+            match submission {
+                Submission::Interactive(interactive) => {
+                    match (
+                        slack::callback::decode(&interactive.callback_id),
+                        interactive.actions.first(),
+                    ) {
+                        (Some(callback), Some(action)) => handle_action(
+                            &callback,
+                            &action.name,
+                            &action.value,
+                            &interactive.user.id,
+                            &interactive.trigger_id,
+                            Some(&interactive.response_url),
+                        ),
+                        _ => Response::Json(slack::message::generate_expired_message()),
+                    }
+                }
+                Submission::BlockActions(block_actions) => {
+                    let action = match block_actions.actions.first() {
+                        Some(action) => action,
+                        None => return Response::Error,
+                    };
+                    let (callback, name, value, user_id, trigger_id) = (
+                        slack::callback::decode(&action.block_id),
+                        action.name().to_owned(),
+                        action.value().to_owned(),
+                        block_actions.user.id.clone(),
+                        block_actions.trigger_id.clone(),
+                    );
+                    // Cards in a report answer separately, so the other cards stay usable.
+                    let replace_original = !slack::callback::is_part(&action.block_id);
+                    let response_url = block_actions.response_url.clone();
+                    let job = move || match callback {
+                        Some(callback) => handle_action(
+                            &callback,
+                            &name,
+                            &value,
+                            &user_id,
+                            &trigger_id,
+                            response_url.as_ref().map(String::as_str),
+                        ),
+                        None => Response::Json(slack::message::generate_expired_message()),
+                    };
+                    match block_actions.response_url {
+                        Some(url) => respond_on(&INTERACTIONS, url, replace_original, job),
+                        None => INTERACTIONS.execute(move || {
+                            job();
+                        }),
+                    }
+                    Response::Empty
+                }
+                Submission::ViewSubmission(view_submission) => {
+                    handle_view_submission(&view_submission)
+                }
+                Submission::BlockSuggestion(_) => Response::Unimplemented,
+                Submission::Shortcut(shortcut) => handle_shortcut(&shortcut),
+                Submission::MessageAction(message_action) => {
+                    handle_message_action(message_action)
+                }
             }
         })
         .unwrap_or_else(|| Response::Error)
 }
 
-pub fn handle_submission(body: &str) -> Response {
+/// Registration starts from a draft owned by whoever opens the modal.
+fn show_register_modal(user_id: &str, trigger_id: &str) -> errors::Result<()> {
+    let mut draft = ip::Entry::draft("", SETTINGS.data_path());
+    draft.owner = Some(user_id.to_owned());
+    slack::modal::show_register_entry_modal(&draft, trigger_id, SETTINGS.token())
+}
+
+fn handle_shortcut(shortcut: &slack::submission::Shortcut) -> Response {
+    if shortcut.callback_id != "register_ip" {
+        return Response::Unimplemented;
+    }
+    let (user_id, trigger_id) = (shortcut.user.id.clone(), shortcut.trigger_id.clone());
+    INTERACTIONS.execute(move || {
+        if !permitted(&user_id, None) {
+            deny_action(&user_id, "", "register_ip", None);
+        } else if let Err(e) = show_register_modal(&user_id, &trigger_id) {
+            errors::log("failed to open modal", &e);
+        }
+    });
+    Response::Empty
+}
+
+fn handle_message_action(message_action: slack::submission::MessageAction) -> Response {
+    if message_action.callback_id != "lookup_ips" {
+        return Response::Unimplemented;
+    }
+    let ips = unfurl::extract_ips(&message_action.message.text);
+    let response_url = message_action.response_url;
+    WORKERS.execute(move || {
+        let message = if ips.is_empty() {
+            slack::message::generate_no_ip_message()
+        } else {
+            let found = ips
+                .into_iter()
+                .map(|ip| {
+                    let entry = ip::Entry::from_ip(&ip, SETTINGS.data_path());
+                    (ip, entry)
+                })
+                .collect::<Vec<_>>();
+            with_visibility(&slack::message::generate_lookup_message(&found), false)
+        };
+        if let Err(e) = slack::respond(&response_url, &message, false) {
+            errors::log(&format!("failed to respond to {}", response_url), &e);
+        }
+    });
+    Response::Empty
+}
+
+pub fn handle_options(body: &str, signature: Option<&Signature>) -> Response {
     slack::submission::Submission::from_str(body)
         .ok()
         .map(|submission| {
             use slack::submission::Submission;
+            if !authorize(body, signature, submission.token()) {
+                return Response::Unauthorized;
+            }
             match submission {
-                Submission::Interactive(interactive) => {
-                    let mut split = interactive.callback_id.split('-');
-                    let typ = split.next().unwrap();
-                    let para = split.next().unwrap();
-                    match typ {
-                        "ip" => ip::Entry::from_ip(para, SETTINGS.data_path())
-                            .map(|mut entry| {
-                                let action = &interactive.actions[0];
-                                match action.name.as_ref() {
-                                    "edit_domain" => {
-                                        slack::dialog::show_edit_domain_dialog(
-                                            &entry,
-                                            &interactive.trigger_id,
-                                            SETTINGS.token(),
-                                        ).unwrap();
-                                        Response::Empty
-                                    }
-                                    "toggle_using" => {
-                                        entry.using = !entry.using;
-                                        entry.save().unwrap();
-                                        Response::Json(slack::message::generate_ip_message(&entry))
-                                    }
-                                    "edit_port" => {
-                                        slack::dialog::show_edit_port_dialog(
-                                            &entry.ip,
-                                            &action.value,
-                                            &interactive.trigger_id,
-                                            SETTINGS.token(),
-                                        ).unwrap();
-                                        Response::Empty
-                                    }
-                                    "add_port" => {
-                                        slack::dialog::show_add_port_dialog(
-                                            &entry.ip,
-                                            &interactive.trigger_id,
-                                            SETTINGS.token(),
-                                        ).unwrap();
-                                        Response::Empty
-                                    }
-                                    "edit_description" => {
-                                        slack::dialog::show_edit_description_dialog(
-                                            &entry,
-                                            &interactive.trigger_id,
-                                            SETTINGS.token(),
-                                        ).unwrap();
-                                        Response::Empty
-                                    }
-                                    "refresh" => {
-                                        Response::Json(slack::message::generate_ip_message(&entry))
-                                    }
-                                    "delete_entry" => {
-                                        entry.delete().unwrap();
-                                        Response::Json(slack::message::generate_deleted_message())
-                                    }
-                                    _ => Response::Unimplemented,
-                                }
-                            })
-                            .unwrap_or_else(|| {
-                                Response::Json(slack::message::generate_inexist_message())
-                            }),
-                        "list" => Response::Json(slack::message::generate_list_message(
-                            &ip::Entry::list(SETTINGS.data_path()),
-                            interactive.actions[0].value.parse::<usize>().unwrap() + 1,
-                        )),
-                        "query" => Response::Json(slack::message::generate_query_message(
-                            para,
-                            &ip::Entry::search(para, SETTINGS.data_path()),
-                            interactive.actions[0].value.parse::<usize>().unwrap() + 1,
-                        )),
-                        "create_new" => {
-                            Response::Json(if interactive.actions[0].value == "create_new_entry" {
-                                slack::message::generate_ip_message(
-                                    &ip::Entry::new(para, SETTINGS.data_path()).unwrap(),
-                                )
-                            } else {
-                                slack::message::generate_cancelled_message()
-                            })
+                Submission::BlockSuggestion(suggestion) => {
+                    let query = suggestion.value.trim();
+                    let mut entries = if query.is_empty() {
+                        ip::Entry::list(SETTINGS.data_path())
+                    } else {
+                        ip::Entry::search(query, SETTINGS.data_path())
+                    };
+                    let user_id = &suggestion.user.id;
+                    match suggestion.action_id.as_str() {
+                        "unused_ip" => entries.retain(|entry| !entry.using),
+                        "transferable_ip" => {
+                            entries.retain(|entry| permitted(user_id, Some(entry)))
                         }
-                        _ => Response::Unimplemented,
+                        _ => (),
                     }
+                    Response::Json(slack::message::generate_entry_options(&entries))
                 }
-                Submission::Dialog(dialog) => {
-                    let mut split = dialog.callback_id.split('-');
-                    let typ = split.next().unwrap();
-                    let ip = split.next().unwrap();
-                    ip::Entry::from_ip(ip, SETTINGS.data_path())
-                        .and_then(|mut entry| {
-                            match typ {
-                                "edit_domain" => {
-                                    if let Some(ref domain) = dialog.submission["domain"] {
-                                        entry.domain = Some(domain.clone());
-                                    } else {
-                                        entry.domain = None;
-                                    }
-                                }
-                                "edit_description" => if let Some(ref description) =
-                                    dialog.submission["description"]
-                                {
-                                    entry.description = Some(description.clone());
-                                } else {
-                                    entry.description = None;
-                                },
-                                "edit_port" => {
-                                    let (key, val) = dialog.submission.iter().nth(0).unwrap();
-                                    if let Some(i) = entry
-                                        .open_ports
-                                        .iter()
-                                        .position(|p| format!("{}", p) == *key)
-                                    {
-                                        if let Some(ref port) = *val {
-                                            if let Some(p) = entry.open_ports.get_mut(i) {
-                                                if let Ok(port) = (*port).parse::<u32>() {
-                                                    *p = port;
-                                                }
-                                            }
-                                        } else {
-                                            entry.open_ports.remove(i);
-                                        }
-                                    }
-                                }
-                                "add_port" => {
-                                    entry.open_ports.append(&mut dialog
-                                        .submission
-                                        .values()
-                                        .filter_map(|k| k.clone())
-                                        .filter_map(|k| k.parse::<u32>().ok())
-                                        .collect());
-                                }
-                                _ => (),
-                            };
-                            entry.save().ok()
-                        })
-                        .map(|_| Response::Empty)
-                        .unwrap_or_else(|| Response::Error)
-                }
+                _ => Response::Unimplemented,
             }
         })
         .unwrap_or_else(|| Response::Error)
 }
+
+fn reply_in_thread(event: slack::event::Event) {
+    lazy_static! {
+        static ref REGEX_MENTION: regex::Regex = regex::Regex::new(r"<@[A-Z0-9]+>").unwrap();
+    }
+
+    let (channel, ts, user_id) = match (event.channel, event.thread_ts.or(event.ts), event.user) {
+        (Some(channel), Some(ts), Some(user_id)) => (channel, ts, user_id),
+        _ => return,
+    };
+    let text = REGEX_MENTION
+        .replace_all(&event.text.unwrap_or_default(), "")
+        .trim()
+        .to_owned();
+    WORKERS.execute(move || {
+        let message = match panic::catch_unwind(|| dispatch_command(&text, &user_id)) {
+            Ok(Response::Json(message)) => message,
+            _ => slack::message::generate_error_message(),
+        };
+        if let Err(e) = slack::post_reply(&channel, &ts, &message, SETTINGS.token()) {
+            errors::log(&format!("failed to reply in {}", channel), &e);
+        }
+    });
+}
+
+fn unfurl(event: slack::event::Event, authed_users: &[String]) {
+    let settings = SETTINGS.unfurl();
+    let (channel, ts, text) = match (event.channel, event.thread_ts.or(event.ts), event.text) {
+        (Some(channel), Some(ts), Some(text)) => (channel, ts, text),
+        _ => return,
+    };
+    if !settings.channels.contains(&channel)
+        || authed_users
+            .iter()
+            .any(|user| text.contains(&format!("<@{}>", user)))
+    {
+        return;
+    }
+    let now = probe::now();
+    let entries = unfurl::extract_ips(&text)
+        .iter()
+        .filter_map(|ip| ip::Entry::from_ip(ip, SETTINGS.data_path()))
+        .filter(|entry| UNFURLED.allow(&channel, &entry.ip, now, settings.cooldown))
+        .take(settings.max_per_message)
+        .collect::<Vec<_>>();
+    if entries.is_empty() {
+        return;
+    }
+    WORKERS.execute(move || {
+        for entry in entries {
+            if let Err(e) = slack::post_reply(
+                &channel,
+                &ts,
+                &slack::message::generate_compact_ip_message(&entry),
+                SETTINGS.token(),
+            ) {
+                errors::log(&format!("failed to unfurl {} in {}", entry.ip, channel), &e);
+            }
+        }
+    });
+}
+
+pub fn handle_event(body: &str, signature: Option<&Signature>) -> Response {
+    use slack::event::Request;
+
+    match Request::from_str(body) {
+        Ok(ref request) if !authorize(body, signature, request.token()) => {
+            Response::Unauthorized
+        }
+        Ok(Request::UrlVerification { challenge, .. }) => {
+            Response::Json(json!({ "challenge": challenge }).to_string())
+        }
+        Ok(Request::EventCallback {
+            event,
+            authed_users,
+            ..
+        }) => {
+            if event.bot_id.is_some() || event.subtype.is_some() {
+                return Response::Empty;
+            }
+            match event.kind.as_str() {
+                "app_home_opened" => if let Some(user_id) = event.user {
+                    if event.tab.as_ref().map(String::as_str).unwrap_or("home") == "home" {
+                        WORKERS.execute(move || publish_home(&user_id));
+                    }
+                },
+                "app_mention" => reply_in_thread(event),
+                "message" => match event.channel_type.as_ref().map(String::as_str) {
+                    Some("im") => reply_in_thread(event),
+                    Some("channel") => unfurl(event, &authed_users),
+                    _ => (),
+                },
+                _ => (),
+            }
+            Response::Empty
+        }
+        Err(_) => Response::Error,
+    }
+}
@@ -1,22 +1,90 @@
 extern crate ip_manager;
 extern crate tiny_http;
 
+use std::env::args;
 use ip_manager::*;
-use tiny_http::{Header, Method, ResponseBox, Server};
+use tiny_http::{Header, Method, Request, ResponseBox, Server};
+
+const USAGE: &str = "usage: ip-manager [settings [hosts|ssh-config|sweep]]";
+const COMMANDS: &[&str] = &["hosts", "ssh-config", "sweep"];
 
 fn main() {
+    // The first argument is always the settings path, which `SETTINGS` reads on first use.
+    let settings = args().nth(1);
+    if settings.map_or(false, |path| COMMANDS.contains(&path.as_str())) || args().count() > 3 {
+        eprintln!("{}", USAGE);
+        std::process::exit(1);
+    }
+    match args().nth(2).as_ref().map(String::as_str) {
+        Some("hosts") => {
+            print!("{}", export(&Export::Hosts));
+            return;
+        }
+        Some("ssh-config") => {
+            print!("{}", export(&Export::SshConfig));
+            return;
+        }
+        Some("sweep") => {
+            for ip in sweep() {
+                println!("{}", ip);
+            }
+            return;
+        }
+        Some(other) => {
+            eprintln!("unknown command: {}", other);
+            eprintln!("{}", USAGE);
+            std::process::exit(1);
+        }
+        None => (),
+    }
+
+    spawn_background_jobs();
+
     let server = Server::http("localhost:8000").unwrap();
 
     server.incoming_requests().for_each(|mut request| {
         let mut body = String::new();
+        let url = request.url().to_owned();
+        let (path, query) = match url.find('?') {
+            Some(i) => (&url[..i], &url[i + 1..]),
+            None => (&url[..], ""),
+        };
+        let signature = header(&request, "X-Slack-Request-Timestamp")
+            .and_then(|timestamp| {
+                header(&request, "X-Slack-Signature").map(|signature| Signature {
+                    timestamp,
+                    signature,
+                })
+            });
         request
             .as_reader()
             .read_to_string(&mut body)
             .ok()
-            .map(|_| match (request.method(), request.url()) {
-                (&Method::Post, "//command") => resp_into_resp(handle_slash_command(&body)),
-                (&Method::Post, "//submission") => resp_into_resp(handle_submission(&body)),
-                (_, "//command") | (_, "//submission") => tiny_http::Response::empty(405).boxed(),
+            .map(|_| match (request.method(), path) {
+                (&Method::Post, "//command") => {
+                    resp_into_resp(handle_slash_command(&body, signature.as_ref()))
+                }
+                (&Method::Post, "//submission") => {
+                    resp_into_resp(handle_submission(&body, signature.as_ref()))
+                }
+                (&Method::Post, "//events") => {
+                    resp_into_resp(handle_event(&body, signature.as_ref()))
+                }
+                (&Method::Post, "//options") => {
+                    resp_into_resp(handle_options(&body, signature.as_ref()))
+                }
+                (&Method::Get, "//export/hosts") => {
+                    resp_into_resp(handle_export(&Export::Hosts, query))
+                }
+                (&Method::Get, "//export/ssh_config") => {
+                    resp_into_resp(handle_export(&Export::SshConfig, query))
+                }
+                (_, "//command")
+                | (_, "//submission")
+                | (_, "//events")
+                | (_, "//options")
+                | (_, "//export/hosts")
+                | (_, "//export/ssh_config") => tiny_http::Response::empty(405).boxed(),
                 _ => tiny_http::Response::empty(404).boxed(),
             })
             .and_then(|resp| request.respond(resp).ok())
@@ -24,6 +92,14 @@ fn main() {
     });
 }
 
+fn header(request: &Request, field: &'static str) -> Option<String> {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv(field))
+        .map(|header| header.value.as_str().to_owned())
+}
+
 fn resp_into_resp(resp: Response) -> ResponseBox {
     match resp {
         Response::Unimplemented => tiny_http::Response::empty(501).boxed(),
@@ -35,6 +111,13 @@ fn resp_into_resp(resp: Response) -> ResponseBox {
                 Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap(),
             )
             .boxed(),
+        Response::Text(s) => tiny_http::Response::from_string(s)
+            .with_status_code(200)
+            .with_header(
+                Header::from_bytes(&b"Content-Type"[..], &b"text/plain; charset=utf-8"[..])
+                    .unwrap(),
+            )
+            .boxed(),
         Response::Error => tiny_http::Response::empty(500).boxed(),
     }
 }
@@ -0,0 +1,261 @@
+extern crate serde_json;
+
+use ip::Entry;
+use subnet::Subnet;
+
+/// Larger pages would overflow the block limit of a message.
+pub const MAX_SIZE: usize = 10;
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum Cursor {
+    First,
+    Last,
+    /// `offset` is where the next page started when the cursor was made.
+    After { ip: String, offset: usize },
+    /// `offset` is where the current page started when the cursor was made.
+    Before { ip: String, offset: usize },
+    /// Zero-based page number, as chosen from the page select.
+    Page(usize),
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Sort {
+    Ip,
+    Domain,
+    Updated,
+    Owner,
+}
+
+impl Default for Sort {
+    fn default() -> Self {
+        Sort::Ip
+    }
+}
+
+impl Sort {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "ip" => Some(Sort::Ip),
+            "domain" => Some(Sort::Domain),
+            "updated" => Some(Sort::Updated),
+            "owner" => Some(Sort::Owner),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Group {
+    Subnet,
+    Tag,
+    Status,
+}
+
+impl Group {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "subnet" => Some(Group::Subnet),
+            "tag" => Some(Group::Tag),
+            "status" => Some(Group::Status),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct Options {
+    #[serde(default)] pub sort: Sort,
+    #[serde(default, skip_serializing_if = "Option::is_none")] pub group: Option<Group>,
+    #[serde(default, skip_serializing_if = "Option::is_none")] pub size: Option<usize>,
+}
+
+impl Options {
+    /// The size given with `--size`, or `default` from the settings.
+    pub fn size(&self, default: usize) -> usize {
+        self.size.unwrap_or(default).max(1).min(MAX_SIZE)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Page {
+    #[serde(default, skip_serializing_if = "Option::is_none")] pub query: Option<String>,
+    pub cursor: Cursor,
+    #[serde(default)] pub options: Options,
+}
+
+impl Page {
+    pub fn new(query: Option<String>, options: Options) -> Self {
+        Page {
+            query,
+            cursor: Cursor::First,
+            options,
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        serde_json::from_str(s).ok()
+    }
+
+    pub fn to_string(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    pub fn with_cursor(&self, cursor: Cursor) -> Self {
+        Page {
+            cursor,
+            ..self.clone()
+        }
+    }
+}
+
+pub fn label(entry: &Entry, group: Group, subnets: &[Subnet]) -> String {
+    match group {
+        Group::Subnet => subnets
+            .iter()
+            .find(|subnet| subnet.contains(&entry.ip))
+            .map(|subnet| subnet.to_string())
+            .unwrap_or_else(|| "기타".to_owned()),
+        Group::Tag => entry
+            .tags
+            .iter()
+            .min()
+            .cloned()
+            .unwrap_or_else(|| "태그 없음".to_owned()),
+        Group::Status => if entry.using { "사용중" } else { "미사용" }.to_owned(),
+    }
+}
+
+/// `entries` must come sorted by IP, as `Entry::list` returns them; ties keep that order.
+pub fn arrange(entries: &mut [Entry], options: &Options, subnets: &[Subnet]) {
+    match options.sort {
+        Sort::Ip => (),
+        Sort::Domain => entries.sort_by(|a, b| {
+            a.domain
+                .is_none()
+                .cmp(&b.domain.is_none())
+                .then_with(|| a.domain.cmp(&b.domain))
+        }),
+        Sort::Updated => entries.sort_by(|a, b| b.updated.cmp(&a.updated)),
+        Sort::Owner => entries.sort_by(|a, b| {
+            a.owner
+                .is_none()
+                .cmp(&b.owner.is_none())
+                .then_with(|| a.owner.cmp(&b.owner))
+        }),
+    }
+    if let Some(group) = options.group {
+        entries.sort_by_key(|entry| label(entry, group, subnets));
+    }
+}
+
+/// Cursors point at the IP at the edge of the previous page, so that pages stay put when
+/// entries are added or removed elsewhere. If that entry is gone, the offset it had is used,
+/// whatever order the list is in. Returns the index of the first shown entry along with the
+/// page itself.
+pub fn slice<'a>(entries: &'a [Entry], cursor: &Cursor, size: usize) -> (usize, &'a [Entry]) {
+    let size = size.max(1);
+    let len = entries.len();
+    let last = len.saturating_sub(1) / size * size;
+    let position = |ip: &str| entries.iter().position(|entry| entry.ip == ip);
+    let start = match *cursor {
+        Cursor::First => 0,
+        Cursor::Last => last,
+        Cursor::After { ref ip, offset } => position(ip).map(|i| i + 1).unwrap_or(offset),
+        Cursor::Before { ref ip, offset } => position(ip).unwrap_or(offset).saturating_sub(size),
+        Cursor::Page(page) => page.saturating_mul(size),
+    };
+    let start = if start >= len { last } else { start };
+    (start, &entries[start..(start + size).min(len)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn entries(count: u8) -> Vec<Entry> {
+        (1..count + 1)
+            .map(|i| Entry::draft(&format!("10.0.0.{}", i), Path::new("/nonexistent")))
+            .collect()
+    }
+
+    fn ips(entries: &[Entry]) -> Vec<&str> {
+        entries.iter().map(|entry| entry.ip.as_str()).collect()
+    }
+
+    #[test]
+    fn page_cursor_jumps_to_the_page() {
+        let entries = entries(7);
+        let (start, shown) = slice(&entries, &Cursor::Page(1), 3);
+        assert_eq!(start, 3);
+        assert_eq!(ips(shown), vec!["10.0.0.4", "10.0.0.5", "10.0.0.6"]);
+        let (start, shown) = slice(&entries, &Cursor::Page(9), 3);
+        assert_eq!(start, 6);
+        assert_eq!(ips(shown), vec!["10.0.0.7"]);
+    }
+
+    #[test]
+    fn size_is_clamped() {
+        assert_eq!(Options::default().size(8), 8);
+        assert_eq!(Options::default().size(0), 1);
+        let options = Options {
+            size: Some(50),
+            ..Options::default()
+        };
+        assert_eq!(options.size(8), MAX_SIZE);
+    }
+
+    #[test]
+    fn missing_cursor_entry_falls_back_to_its_offset() {
+        // Not in IP order, so an estimate by IP would land on the first page.
+        let mut entries = entries(9);
+        entries.reverse();
+        let after = Cursor::After {
+            ip: "10.0.0.7".to_owned(),
+            offset: 3,
+        };
+        let before = Cursor::Before {
+            ip: "10.0.0.3".to_owned(),
+            offset: 6,
+        };
+        let middle = vec!["10.0.0.6", "10.0.0.5", "10.0.0.4"];
+        assert_eq!(ips(slice(&entries, &after, 3).1), middle);
+        assert_eq!(ips(slice(&entries, &before, 3).1), middle);
+
+        entries.retain(|entry| entry.ip != "10.0.0.7" && entry.ip != "10.0.0.3");
+        assert_eq!(slice(&entries, &after, 3).0, 3);
+        assert_eq!(slice(&entries, &before, 3).0, 3);
+    }
+
+    #[test]
+    fn arrange_sorts_then_groups() {
+        let mut entries = entries(4);
+        entries[0].domain = Some("b.example".to_owned());
+        entries[2].domain = Some("a.example".to_owned());
+        entries[1].tags = vec!["gpu".to_owned()];
+        entries[2].tags = vec!["gpu".to_owned()];
+        for (i, entry) in entries.iter_mut().enumerate() {
+            entry.updated = Some(i as u64);
+        }
+
+        let mut sorted = entries.clone();
+        let options = Options {
+            sort: Sort::Domain,
+            ..Options::default()
+        };
+        arrange(&mut sorted, &options, &[]);
+        assert_eq!(ips(&sorted), vec!["10.0.0.3", "10.0.0.1", "10.0.0.2", "10.0.0.4"]);
+
+        let mut sorted = entries.clone();
+        let options = Options {
+            sort: Sort::Updated,
+            group: Some(Group::Tag),
+            size: None,
+        };
+        arrange(&mut sorted, &options, &[]);
+        assert_eq!(ips(&sorted), vec!["10.0.0.3", "10.0.0.2", "10.0.0.4", "10.0.0.1"]);
+    }
+}
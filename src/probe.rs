@@ -0,0 +1,199 @@
+use std::fs::File;
+use std::io::Read;
+use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use errors;
+use ip::{self, Entry};
+use settings::ProbeSettings;
+
+pub struct Probe {
+    /// `None` when nothing conclusive could be probed: there were no ports to scan, ICMP is off,
+    /// and the ARP cache, which only holds hosts that talked recently, has no entry.
+    pub responding: Option<bool>,
+    pub ports: Vec<u32>,
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+pub fn scan_ports(ip: &IpAddr, ports: &[u32], timeout: Duration) -> Vec<u32> {
+    ports
+        .iter()
+        .filter(|port| **port <= u32::from(u16::max_value()))
+        .filter(|port| {
+            TcpStream::connect_timeout(&SocketAddr::new(*ip, **port as u16), timeout).is_ok()
+        })
+        .cloned()
+        .collect()
+}
+
+fn ping(ip: &IpAddr, timeout: Duration) -> bool {
+    Command::new("ping")
+        .arg("-c")
+        .arg("1")
+        .arg("-W")
+        .arg(format!("{}", timeout.as_secs().max(1)))
+        .arg(format!("{}", ip))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+fn in_arp_table(ip: &IpAddr) -> bool {
+    let mut content = String::new();
+    if File::open("/proc/net/arp")
+        .and_then(|mut file| file.read_to_string(&mut content))
+        .is_err()
+    {
+        return false;
+    }
+    let ip = format!("{}", ip);
+    content.lines().skip(1).any(|line| {
+        let mut columns = line.split_whitespace();
+        columns.next() == Some(&ip) && columns.nth(1).map(|flags| flags != "0x0").unwrap_or(false)
+    })
+}
+
+pub fn probe(ip: &IpAddr, ports: &[u32], settings: &ProbeSettings) -> Probe {
+    let timeout = Duration::from_millis(settings.timeout_ms);
+    let open = scan_ports(ip, ports, timeout);
+    let responding = if !open.is_empty() || (settings.icmp && ping(ip, timeout))
+        || (settings.arp && in_arp_table(ip))
+    {
+        Some(true)
+    } else if !ports.is_empty() || settings.icmp {
+        Some(false)
+    } else {
+        None
+    };
+    Probe {
+        responding,
+        ports: open,
+    }
+}
+
+/// Returns `false` if the probe was inconclusive, in which case the entry keeps the result of
+/// the last conclusive one.
+pub fn probe_entry(entry: &mut Entry, settings: &ProbeSettings) -> bool {
+    let ip = match entry.ip.parse::<IpAddr>() {
+        Ok(ip) => ip,
+        Err(_) => return false,
+    };
+    let result = probe(&ip, &entry.open_ports, settings);
+    let responding = match result.responding {
+        Some(responding) => responding,
+        None => return false,
+    };
+    let time = now();
+    entry.last_probed = Some(time);
+    if responding {
+        entry.last_seen = Some(time);
+    }
+    entry.reachable_ports = result.ports;
+    true
+}
+
+pub fn is_responding(entry: &Entry) -> bool {
+    entry.last_probed.is_some() && entry.last_seen == entry.last_probed
+}
+
+pub fn mismatches(entry: &Entry) -> Vec<String> {
+    if entry.last_probed.is_none() {
+        return vec![];
+    }
+    let responding = is_responding(entry);
+    let mut v = vec![];
+    if !entry.using && responding {
+        v.push("미사용으로 표시되어 있지만 응답합니다".to_owned());
+    }
+    if entry.using && !responding {
+        v.push("사용중으로 표시되어 있지만 응답하지 않습니다".to_owned());
+    }
+    v.extend(
+        entry
+            .open_ports
+            .iter()
+            .filter(|port| !entry.reachable_ports.contains(port))
+            .map(|port| format!("포트 {}이(가) 목록에 있지만 닫혀 있습니다", port)),
+    );
+    v
+}
+
+fn probe_all(data_path: &Path, settings: &ProbeSettings) {
+    for mut probed in Entry::list(data_path) {
+        if !probe_entry(&mut probed, settings) {
+            continue;
+        }
+        // Reload under the store lock so edits made while probing aren't overwritten.
+        let store = ip::lock();
+        if let Some(mut entry) = Entry::from_ip(&probed.ip, data_path) {
+            entry.last_probed = probed.last_probed;
+            entry.last_seen = probed.last_seen;
+            entry.reachable_ports = probed.reachable_ports;
+            if let Err(e) = store.commit(&[(Some(&entry), Some(&entry))]) {
+                errors::log(&format!("failed to save probe of {}", entry.ip), &e);
+            }
+        }
+    }
+}
+
+pub fn spawn(data_path: PathBuf, settings: ProbeSettings) {
+    thread::spawn(move || loop {
+        probe_all(&data_path, &settings);
+        thread::sleep(Duration::from_secs(settings.interval));
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn settings(icmp: bool, arp: bool) -> ProbeSettings {
+        ProbeSettings {
+            icmp,
+            arp,
+            ..ProbeSettings::default()
+        }
+    }
+
+    #[test]
+    fn probe_without_methods_is_inconclusive() {
+        let ip = "127.0.0.1".parse().unwrap();
+        assert!(probe(&ip, &[], &settings(false, false)).responding.is_none());
+        // A missing ARP entry only means the host has been quiet.
+        let ip = "198.51.100.77".parse().unwrap();
+        assert!(probe(&ip, &[], &settings(false, true)).responding.is_none());
+    }
+
+    #[test]
+    fn unprobed_entry_has_no_mismatches() {
+        let mut entry = Entry::draft("198.51.100.77", Path::new("."));
+        entry.using = true;
+        assert!(!probe_entry(&mut entry, &settings(false, true)));
+        assert!(entry.last_probed.is_none());
+        assert!(mismatches(&entry).is_empty());
+    }
+
+    #[test]
+    fn inconclusive_probe_keeps_the_last_result() {
+        let mut entry = Entry::draft("198.51.100.77", Path::new("."));
+        entry.last_probed = Some(100);
+        entry.last_seen = Some(100);
+        entry.reachable_ports = vec![22];
+        assert!(!probe_entry(&mut entry, &settings(false, true)));
+        assert_eq!(entry.last_probed, Some(100));
+        assert!(is_responding(&entry));
+        assert_eq!(entry.reachable_ports, vec![22]);
+    }
+}
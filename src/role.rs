@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use errors;
+use ip::Entry;
+use probe;
+use settings::RoleSettings;
+use slack;
+
+const GROUP_TTL: u64 = 300;
+/// A failed fetch is not retried until this many seconds have passed.
+const FAILURE_TTL: u64 = 30;
+
+pub enum Role {
+    Admin,
+    Member,
+    ReadOnly,
+}
+
+/// Members of a usergroup, cached until the given time.
+/// A failed fetch is cached as `None` so that Slack is not asked again on every check.
+type Members = (u64, Option<Vec<String>>);
+
+fn is_member(user_id: &str, ids: &[String], token: &str) -> bool {
+    lazy_static! {
+        static ref GROUPS: Mutex<HashMap<String, Members>> = Mutex::new(HashMap::new());
+    }
+
+    let contains = |members: &Option<Vec<String>>| {
+        members
+            .as_ref()
+            .map(|users| users.iter().any(|user| user == user_id))
+            .unwrap_or(false)
+    };
+
+    ids.iter().any(|id| {
+        if id == user_id {
+            return true;
+        }
+        if !id.starts_with('S') {
+            return false;
+        }
+        let now = probe::now();
+        let cached = match GROUPS.lock() {
+            Ok(groups) => groups
+                .get(id)
+                .filter(|&&(expires, _)| expires > now)
+                .map(|&(_, ref members)| contains(members)),
+            Err(_) => return false,
+        };
+        if let Some(member) = cached {
+            return member;
+        }
+
+        // Fetched without holding the lock so that other checks don't wait on Slack.
+        let (expires, members) = match slack::usergroup_members(id, token) {
+            Ok(users) => (now + GROUP_TTL, Some(users)),
+            Err(e) => {
+                errors::log(&format!("failed to fetch members of {}", id), &e);
+                (now + FAILURE_TTL, None)
+            }
+        };
+        let member = contains(&members);
+        if let Ok(mut groups) = GROUPS.lock() {
+            groups.insert(id.clone(), (expires, members));
+        }
+        member
+    })
+}
+
+pub fn role(user_id: &str, settings: &RoleSettings, token: &str) -> Role {
+    if is_member(user_id, &settings.admins, token) {
+        Role::Admin
+    } else if is_member(user_id, &settings.read_only, token) {
+        Role::ReadOnly
+    } else {
+        Role::Member
+    }
+}
+
+/// `entry` is `None` when a new entry is about to be created.
+pub fn permitted(
+    user_id: &str,
+    entry: Option<&Entry>,
+    settings: &RoleSettings,
+    token: &str,
+) -> bool {
+    allows(&role(user_id, settings, token), user_id, entry, settings)
+}
+
+/// Like `permitted`, for a role which has already been looked up. Unlike `role`, this never
+/// asks Slack, so it may be called while the store is locked.
+pub fn allows(role: &Role, user_id: &str, entry: Option<&Entry>, settings: &RoleSettings) -> bool {
+    match *role {
+        Role::Admin => true,
+        Role::ReadOnly => false,
+        Role::Member => match entry {
+            Some(entry) if !settings.admins.is_empty() => {
+                entry.owner.as_ref().map(String::as_str) == Some(user_id)
+            }
+            _ => true,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn settings(admins: &[&str], read_only: &[&str]) -> RoleSettings {
+        RoleSettings {
+            admins: admins.iter().map(|id| id.to_string()).collect(),
+            read_only: read_only.iter().map(|id| id.to_string()).collect(),
+        }
+    }
+
+    fn entry(owner: Option<&str>) -> Entry {
+        let mut entry = Entry::draft("10.0.0.1", Path::new("."));
+        entry.owner = owner.map(str::to_owned);
+        entry
+    }
+
+    #[test]
+    fn admins_may_do_anything_and_read_only_users_nothing() {
+        let settings = settings(&["U0ADMIN"], &["U0VIEWER"]);
+        for entry in &[None, Some(entry(None)), Some(entry(Some("U0OWNER")))] {
+            assert!(permitted("U0ADMIN", entry.as_ref(), &settings, ""));
+            assert!(!permitted("U0VIEWER", entry.as_ref(), &settings, ""));
+        }
+    }
+
+    #[test]
+    fn members_may_only_touch_their_own_entries_once_admins_are_set() {
+        let settings = settings(&["U0ADMIN"], &[]);
+        assert!(permitted("U0OWNER", Some(&entry(Some("U0OWNER"))), &settings, ""));
+        assert!(!permitted("U0OTHER", Some(&entry(Some("U0OWNER"))), &settings, ""));
+        assert!(!permitted("U0OTHER", Some(&entry(None)), &settings, ""));
+        assert!(permitted("U0OTHER", None, &settings, ""));
+    }
+
+    #[test]
+    fn everyone_but_read_only_users_may_edit_without_admins() {
+        let settings = settings(&[], &["U0VIEWER"]);
+        assert!(permitted("U0OTHER", Some(&entry(Some("U0OWNER"))), &settings, ""));
+        assert!(permitted("U0OTHER", Some(&entry(None)), &settings, ""));
+        assert!(!permitted("U0VIEWER", Some(&entry(None)), &settings, ""));
+    }
+}
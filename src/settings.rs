@@ -1,15 +1,171 @@
 extern crate config;
 
 use std::env::args;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use self::config::{Config, File};
 use errors::Result;
+use page;
+use subnet::{self, Subnet};
 
 #[derive(Deserialize)]
 pub struct Settings {
-    verification_token: String,
+    #[serde(default)] verification_token: String,
+    #[serde(default)] signing_secret: Option<String>,
+    #[serde(default)] allow_token_fallback: bool,
     api_token: String,
     data_path: String,
+    #[serde(default = "default_history_path")] history_path: String,
+    #[serde(default)] ssh_ports: Vec<u32>,
+    #[serde(default)] export_token: Option<String>,
+    #[serde(default)] probe: ProbeSettings,
+    #[serde(default)] drift: DriftSettings,
+    #[serde(default)] subnets: Vec<String>,
+    #[serde(default)] discovery: DiscoverySettings,
+    #[serde(default)] dns: DnsSettings,
+    #[serde(default)] unfurl: UnfurlSettings,
+    #[serde(default)] roles: RoleSettings,
+    #[serde(default)] notify: NotifySettings,
+    #[serde(default = "default_workers")] workers: usize,
+    #[serde(default)] response_type: String,
+    #[serde(default = "default_page_size")] page_size: usize,
+}
+
+fn default_history_path() -> String {
+    "./history.jsonl".to_owned()
+}
+
+fn default_page_size() -> usize {
+    8
+}
+
+fn default_workers() -> usize {
+    4
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct ProbeSettings {
+    pub interval: u64,
+    pub timeout_ms: u64,
+    pub icmp: bool,
+    pub arp: bool,
+}
+
+impl Default for ProbeSettings {
+    fn default() -> Self {
+        ProbeSettings {
+            interval: 0,
+            timeout_ms: 500,
+            icmp: false,
+            arp: false,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct DriftSettings {
+    pub interval: u64,
+    pub channel: String,
+    pub ports: Vec<u32>,
+}
+
+impl Default for DriftSettings {
+    fn default() -> Self {
+        DriftSettings {
+            interval: 0,
+            channel: String::new(),
+            ports: vec![21, 22, 23, 25, 53, 80, 443, 3306, 3389, 5432, 5900, 8080],
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct DiscoverySettings {
+    pub interval: u64,
+    pub channel: String,
+    pub path: PathBuf,
+    pub ports: Vec<u32>,
+    pub concurrency: usize,
+}
+
+impl Default for DiscoverySettings {
+    fn default() -> Self {
+        DiscoverySettings {
+            interval: 0,
+            channel: String::new(),
+            path: PathBuf::from("./candidates.toml"),
+            ports: vec![22, 80, 443],
+            concurrency: 32,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct DnsSettings {
+    pub interval: u64,
+    pub channel: String,
+    pub resolver: Option<String>,
+    pub timeout_ms: u64,
+}
+
+impl Default for DnsSettings {
+    fn default() -> Self {
+        DnsSettings {
+            interval: 0,
+            channel: String::new(),
+            resolver: None,
+            timeout_ms: 2000,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct UnfurlSettings {
+    pub channels: Vec<String>,
+    pub cooldown: u64,
+    pub max_per_message: usize,
+}
+
+impl Default for UnfurlSettings {
+    fn default() -> Self {
+        UnfurlSettings {
+            channels: vec![],
+            cooldown: 600,
+            max_per_message: 3,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct RoleSettings {
+    pub admins: Vec<String>,
+    pub read_only: Vec<String>,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct NotifySettings {
+    pub channel: String,
+    pub events: Vec<String>,
+}
+
+impl Default for NotifySettings {
+    fn default() -> Self {
+        NotifySettings {
+            channel: String::new(),
+            events: vec![
+                "create".to_owned(),
+                "edit".to_owned(),
+                "toggle".to_owned(),
+                "delete".to_owned(),
+            ],
+        }
+    }
 }
 
 impl Settings {
@@ -21,11 +177,28 @@ impl Settings {
                 .map(|a| File::from(Path::new(&a)))
                 .unwrap_or_else(|| File::with_name("settings")),
         )?;
-        settings.try_into().map_err(|e| e.into())
+        let settings: Settings = settings.try_into()?;
+        for s in &settings.subnets {
+            if Subnet::from_str(s)?.prefix() < subnet::MIN_PREFIX {
+                bail!("subnet {} is larger than /{}", s, subnet::MIN_PREFIX);
+            }
+        }
+        Ok(settings)
     }
 
     pub fn verify(&self, other: &str) -> bool {
-        self.verification_token == other
+        !self.verification_token.is_empty() && self.verification_token == other
+    }
+
+    pub fn signing_secret(&self) -> Option<&str> {
+        self.signing_secret
+            .as_ref()
+            .map(String::as_str)
+            .filter(|secret| !secret.is_empty())
+    }
+
+    pub fn allow_token_fallback(&self) -> bool {
+        self.allow_token_fallback
     }
 
     pub fn token(&self) -> &str {
@@ -35,4 +208,66 @@ impl Settings {
     pub fn data_path(&self) -> &Path {
         Path::new(&self.data_path)
     }
+
+    pub fn history_path(&self) -> &Path {
+        Path::new(&self.history_path)
+    }
+
+    pub fn ssh_ports(&self) -> &[u32] {
+        &self.ssh_ports
+    }
+
+    pub fn probe(&self) -> &ProbeSettings {
+        &self.probe
+    }
+
+    pub fn drift(&self) -> &DriftSettings {
+        &self.drift
+    }
+
+    pub fn subnets(&self) -> Vec<Subnet> {
+        self.subnets
+            .iter()
+            .filter_map(|s| Subnet::from_str(s).ok())
+            .collect()
+    }
+
+    pub fn discovery(&self) -> &DiscoverySettings {
+        &self.discovery
+    }
+
+    pub fn dns(&self) -> &DnsSettings {
+        &self.dns
+    }
+
+    pub fn unfurl(&self) -> &UnfurlSettings {
+        &self.unfurl
+    }
+
+    pub fn roles(&self) -> &RoleSettings {
+        &self.roles
+    }
+
+    pub fn notify(&self) -> &NotifySettings {
+        &self.notify
+    }
+
+    pub fn share_by_default(&self) -> bool {
+        self.response_type == "in_channel"
+    }
+
+    pub fn page_size(&self) -> usize {
+        self.page_size.max(1).min(page::MAX_SIZE)
+    }
+
+    pub fn workers(&self) -> usize {
+        self.workers
+    }
+
+    pub fn verify_export(&self, other: &str) -> bool {
+        self.export_token
+            .as_ref()
+            .map(|token| !token.is_empty() && token == other)
+            .unwrap_or(false)
+    }
 }
@@ -0,0 +1,50 @@
+use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Mutex;
+use probe;
+
+/// A `response_url` can be used for thirty minutes, so the share button can't outlive this.
+const TTL: u64 = 1800;
+
+lazy_static! {
+    static ref MESSAGES: Mutex<HashMap<String, (u64, String)>> = Mutex::new(HashMap::new());
+}
+
+/// Keeps a rendered message so that its share button posts exactly what was shown.
+/// Returns the key to put into the button.
+pub fn keep(message: &str) -> String {
+    let now = probe::now();
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write(message.as_bytes());
+    hasher.write_u64(now);
+    let key = format!("{:016x}", hasher.finish());
+    if let Ok(mut messages) = MESSAGES.lock() {
+        messages.retain(|_, &mut (expires, _)| expires > now);
+        messages.insert(key.clone(), (now + TTL, message.to_owned()));
+    }
+    key
+}
+
+pub fn get(key: &str) -> Option<String> {
+    let now = probe::now();
+    MESSAGES.lock().ok().and_then(|messages| {
+        messages
+            .get(key)
+            .filter(|&&(expires, _)| expires > now)
+            .map(|&(_, ref message)| message.clone())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kept_messages_are_returned_unchanged() {
+        let key = keep("{\"text\":\"a\"}");
+        assert_ne!(key, keep("{\"text\":\"a\"}"));
+        assert_eq!(get(&key).unwrap(), "{\"text\":\"a\"}");
+        assert!(get("unknown").is_none());
+    }
+}
@@ -0,0 +1,336 @@
+extern crate serde_json;
+
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[derive(Serialize, Clone)]
+pub struct Text {
+    #[serde(rename = "type")] kind: &'static str,
+    pub text: String,
+}
+
+impl Text {
+    pub fn plain<S: Into<String>>(text: S) -> Self {
+        Text {
+            kind: "plain_text",
+            text: text.into(),
+        }
+    }
+
+    pub fn mrkdwn<S: Into<String>>(text: S) -> Self {
+        Text {
+            kind: "mrkdwn",
+            text: text.into(),
+        }
+    }
+
+    pub fn truncate(mut self, max: usize) -> Self {
+        if self.text.chars().count() > max {
+            self.text = self.text.chars().take(max - 1).collect::<String>() + "…";
+        }
+        self
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct Confirm {
+    pub title: Text,
+    pub text: Text,
+    pub confirm: Text,
+    pub deny: Text,
+}
+
+impl Confirm {
+    pub fn new(title: &str, text: &str, confirm: &str, deny: &str) -> Self {
+        Confirm {
+            title: Text::plain(title),
+            text: Text::plain(text),
+            confirm: Text::plain(confirm),
+            deny: Text::plain(deny),
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct Button {
+    pub text: Text,
+    pub action_id: String,
+    pub value: String,
+    #[serde(skip_serializing_if = "Option::is_none")] pub style: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")] pub confirm: Option<Confirm>,
+}
+
+impl Button {
+    pub fn new<S: Into<String>>(text: S, action_id: &str, value: &str) -> Self {
+        Button {
+            text: Text::plain(text).truncate(75),
+            action_id: action_id.to_owned(),
+            value: value.to_owned(),
+            style: None,
+            confirm: None,
+        }
+    }
+
+    pub fn primary(mut self) -> Self {
+        self.style = Some("primary");
+        self
+    }
+
+    pub fn danger(mut self) -> Self {
+        self.style = Some("danger");
+        self
+    }
+
+    pub fn confirm(mut self, confirm: Confirm) -> Self {
+        self.confirm = Some(confirm);
+        self
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct OptionObject {
+    pub text: Text,
+    pub value: String,
+}
+
+impl OptionObject {
+    pub fn new<S: Into<String>>(text: S, value: &str) -> Self {
+        OptionObject {
+            text: Text::plain(text).truncate(75),
+            value: value.to_owned(),
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct PlainTextInput {
+    pub action_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")] pub initial_value: Option<String>,
+    pub multiline: bool,
+}
+
+impl PlainTextInput {
+    pub fn new(action_id: &str, initial_value: Option<String>) -> Self {
+        PlainTextInput {
+            action_id: action_id.to_owned(),
+            initial_value: initial_value.filter(|value| !value.is_empty()),
+            multiline: false,
+        }
+    }
+
+    pub fn multiline(mut self) -> Self {
+        self.multiline = true;
+        self
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct Checkboxes {
+    pub action_id: String,
+    pub options: Vec<OptionObject>,
+    #[serde(skip_serializing_if = "Vec::is_empty")] pub initial_options: Vec<OptionObject>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct StaticSelect {
+    pub action_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")] pub placeholder: Option<Text>,
+    pub options: Vec<OptionObject>,
+    #[serde(skip_serializing_if = "Option::is_none")] pub initial_option: Option<OptionObject>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct UsersSelect {
+    pub action_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")] pub initial_user: Option<String>,
+}
+
+/// Options are loaded from the options load URL as the user types.
+#[derive(Serialize, Clone)]
+pub struct ExternalSelect {
+    pub action_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")] pub placeholder: Option<Text>,
+    pub min_query_length: u32,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Element {
+    Button(Button),
+    PlainTextInput(PlainTextInput),
+    Checkboxes(Checkboxes),
+    StaticSelect(StaticSelect),
+    UsersSelect(UsersSelect),
+    ExternalSelect(ExternalSelect),
+}
+
+#[derive(Serialize, Clone)]
+pub struct Section {
+    #[serde(skip_serializing_if = "Option::is_none")] pub text: Option<Text>,
+    #[serde(skip_serializing_if = "Vec::is_empty")] pub fields: Vec<Text>,
+    #[serde(skip_serializing_if = "Option::is_none")] pub accessory: Option<Element>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct Actions {
+    pub block_id: String,
+    pub elements: Vec<Element>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct Context {
+    pub elements: Vec<Text>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct Input {
+    pub block_id: String,
+    pub label: Text,
+    pub element: Element,
+    pub optional: bool,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Block {
+    Section(Section),
+    Actions(Actions),
+    Context(Context),
+    Input(Input),
+    Divider,
+}
+
+impl Block {
+    pub fn section(text: Text) -> Self {
+        Block::Section(Section {
+            text: Some(text.truncate(3000)),
+            fields: vec![],
+            accessory: None,
+        })
+    }
+
+    pub fn fields(fields: Vec<Text>) -> Self {
+        Block::Section(Section {
+            text: None,
+            fields: fields
+                .into_iter()
+                .take(10)
+                .map(|field| field.truncate(2000))
+                .collect(),
+            accessory: None,
+        })
+    }
+
+    pub fn actions<S: Into<String>>(block_id: S, elements: Vec<Element>) -> Self {
+        Block::Actions(Actions {
+            block_id: block_id.into(),
+            elements,
+        })
+    }
+
+    pub fn context(elements: Vec<Text>) -> Self {
+        Block::Context(Context { elements })
+    }
+
+    pub fn input(block_id: &str, label: &str, element: Element, optional: bool) -> Self {
+        Block::Input(Input {
+            block_id: block_id.to_owned(),
+            label: Text::plain(label),
+            element,
+            optional,
+        })
+    }
+}
+
+#[derive(Serialize)]
+pub struct Message {
+    #[serde(skip_serializing_if = "Option::is_none")] pub response_type: Option<&'static str>,
+    pub text: String,
+    pub blocks: Vec<Block>,
+}
+
+impl Message {
+    pub fn new<S: AsRef<str>>(text: S, blocks: Vec<Block>) -> Self {
+        Message {
+            response_type: None,
+            text: escape(text.as_ref()),
+            blocks,
+        }
+    }
+
+    pub fn text<S: AsRef<str>>(text: S) -> Self {
+        Message::new(&text, vec![Block::section(Text::plain(text.as_ref()))])
+    }
+
+    pub fn ephemeral(mut self) -> Self {
+        self.response_type = Some("ephemeral");
+        self
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+/// Response to a `block_suggestion` request.
+#[derive(Serialize)]
+pub struct Suggestions {
+    pub options: Vec<OptionObject>,
+}
+
+impl Suggestions {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+#[derive(Serialize)]
+pub struct View {
+    #[serde(rename = "type")] kind: &'static str,
+    #[serde(skip_serializing_if = "String::is_empty")] pub callback_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")] pub title: Option<Text>,
+    #[serde(skip_serializing_if = "Option::is_none")] pub submit: Option<Text>,
+    #[serde(skip_serializing_if = "Option::is_none")] pub close: Option<Text>,
+    #[serde(skip_serializing_if = "String::is_empty")] pub private_metadata: String,
+    pub blocks: Vec<Block>,
+}
+
+impl View {
+    pub fn modal<S: Into<String>>(callback_id: S, title: &str, blocks: Vec<Block>) -> Self {
+        View {
+            kind: "modal",
+            callback_id: callback_id.into(),
+            title: Some(Text::plain(title).truncate(24)),
+            submit: Some(Text::plain("확인")),
+            close: Some(Text::plain("취소")),
+            private_metadata: String::new(),
+            blocks,
+        }
+    }
+
+    pub fn home(blocks: Vec<Block>) -> Self {
+        View {
+            kind: "home",
+            callback_id: String::new(),
+            title: None,
+            submit: None,
+            close: None,
+            private_metadata: String::new(),
+            blocks,
+        }
+    }
+
+    pub fn without_submit(mut self) -> Self {
+        self.submit = None;
+        self.close = Some(Text::plain("닫기"));
+        self
+    }
+
+    pub fn private_metadata<S: Into<String>>(mut self, private_metadata: S) -> Self {
+        self.private_metadata = private_metadata.into();
+        self
+    }
+}
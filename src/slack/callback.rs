@@ -0,0 +1,118 @@
+extern crate serde;
+extern crate serde_json;
+
+use self::serde::de::DeserializeOwned;
+use self::serde::ser::Serialize;
+
+/// Bump whenever a variant below changes shape, so that buttons and modals left over from
+/// older messages are reported as expired instead of being misread.
+pub const VERSION: u32 = 2;
+
+/// Encoded into the `block_id` of action blocks.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "t", rename_all = "snake_case")]
+pub enum Action {
+    Ip { ip: String },
+    Drift { ip: String },
+    List,
+    Query,
+    Open,
+    CreateNew { ip: String },
+    Share { key: String },
+    Home,
+}
+
+/// Encoded into the `callback_id` of modals.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "t", rename_all = "snake_case")]
+pub enum View {
+    EditDomain { ip: String },
+    EditDescription { ip: String },
+    EditPort { ip: String },
+    AddPort { ip: String },
+    EditEntry { ip: String },
+    RegisterEntry,
+    Bulk,
+    BulkConfirm,
+    Search,
+    Allocate,
+    Transfer,
+}
+
+#[derive(Serialize)]
+struct EnvelopeRef<'a, T: 'a> {
+    v: u32,
+    c: &'a T,
+    #[serde(skip_serializing_if = "Option::is_none")] k: Option<&'a str>,
+    #[serde(skip_serializing_if = "is_false")] p: bool,
+}
+
+fn is_false(b: &bool) -> bool {
+    !*b
+}
+
+#[derive(Deserialize)]
+struct Envelope<T> {
+    v: u32,
+    c: T,
+}
+
+#[derive(Deserialize)]
+struct Part {
+    #[serde(default)] p: bool,
+}
+
+fn encode_envelope<T: Serialize>(callback: &T, key: Option<&str>, part: bool) -> String {
+    serde_json::to_string(&EnvelopeRef {
+        v: VERSION,
+        c: callback,
+        k: key,
+        p: part,
+    }).unwrap_or_default()
+}
+
+pub fn encode<T: Serialize>(callback: &T) -> String {
+    encode_envelope(callback, None, false)
+}
+
+/// `key` only keeps ids unique when a message has several blocks for the same callback.
+pub fn encode_keyed<T: Serialize>(callback: &T, key: &str) -> String {
+    encode_envelope(callback, Some(key), false)
+}
+
+/// For blocks which are one of several cards in a message, so that answering them leaves the
+/// rest of the message in place.
+pub fn encode_part<T: Serialize>(callback: &T) -> String {
+    encode_envelope(callback, None, true)
+}
+
+pub fn is_part(s: &str) -> bool {
+    serde_json::from_str::<Part>(s)
+        .map(|part| part.p)
+        .unwrap_or(false)
+}
+
+pub fn decode<T: DeserializeOwned>(s: &str) -> Option<T> {
+    serde_json::from_str::<Envelope<T>>(s)
+        .ok()
+        .filter(|envelope| envelope.v == VERSION)
+        .map(|envelope| envelope.c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parts_decode_like_plain_callbacks() {
+        let ip = "10.0.0.1".to_owned();
+        let part = encode_part(&Action::CreateNew { ip: ip.clone() });
+        assert!(is_part(&part));
+        assert!(!is_part(&encode(&Action::CreateNew { ip: ip.clone() })));
+        assert!(!is_part(&encode_keyed(&Action::Ip { ip: ip.clone() }, "more")));
+        match decode::<Action>(&part) {
+            Some(Action::CreateNew { ip: decoded }) => assert_eq!(decoded, ip),
+            _ => panic!("part did not decode"),
+        }
+    }
+}
@@ -0,0 +1,55 @@
+extern crate serde_json;
+
+use std::str::FromStr;
+use errors::{Error, Result};
+
+#[derive(Deserialize)]
+#[serde(tag = "type")]
+pub enum Request {
+    #[serde(rename = "url_verification")]
+    UrlVerification {
+        #[serde(default)] token: String,
+        challenge: String,
+    },
+    #[serde(rename = "event_callback")]
+    EventCallback {
+        #[serde(default)] token: String,
+        #[serde(default)] authed_users: Vec<String>,
+        event: Event,
+    },
+}
+
+impl FromStr for Request {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        serde_json::from_str(s).map_err(|e| e.into())
+    }
+}
+
+impl Request {
+    pub fn from_str(s: &str) -> Result<Self> {
+        FromStr::from_str(s)
+    }
+
+    pub fn token(&self) -> &str {
+        match *self {
+            Request::UrlVerification { ref token, .. } => token,
+            Request::EventCallback { ref token, .. } => token,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Event {
+    #[serde(rename = "type")] pub kind: String,
+    pub user: Option<String>,
+    pub tab: Option<String>,
+    pub text: Option<String>,
+    pub channel: Option<String>,
+    pub channel_type: Option<String>,
+    pub ts: Option<String>,
+    pub thread_ts: Option<String>,
+    pub bot_id: Option<String>,
+    pub subtype: Option<String>,
+}
@@ -0,0 +1,146 @@
+use ip::Entry;
+use errors::Result;
+use super::callback::{self, Action};
+use super::block::{escape, Block, Button, Element, ExternalSelect, Text, View};
+
+pub struct Usage {
+    pub subnet: String,
+    pub using: usize,
+    pub registered: usize,
+    pub size: usize,
+}
+
+fn entry_line(entry: &Entry) -> String {
+    format!(
+        "`{}` {}{}",
+        entry.ip,
+        entry
+            .domain
+            .as_ref()
+            .map(|domain| format!("{} · ", escape(domain)))
+            .unwrap_or_default(),
+        if entry.using { "사용중" } else { "미사용" }
+    )
+}
+
+fn generate_own_block(own: &[Entry]) -> Block {
+    let mut lines = vec![format!("*내 IP* ({})", own.len())];
+    lines.extend(own.iter().take(20).map(entry_line));
+    if own.len() > 20 {
+        lines.push(format!("외 {}개", own.len() - 20));
+    } else if own.is_empty() {
+        lines.push("담당하고 있는 IP가 없습니다.".to_owned());
+    }
+    Block::section(Text::mrkdwn(lines.join("\n")))
+}
+
+fn generate_recent_block(recent: &[Entry]) -> Block {
+    let mut lines = vec!["*최근 변경*".to_owned()];
+    lines.extend(recent.iter().map(|entry| match entry.updated {
+        Some(updated) => format!(
+            "{} · <!date^{}^{{date_short_pretty}} {{time}}|{}>",
+            entry_line(entry),
+            updated,
+            updated
+        ),
+        None => entry_line(entry),
+    }));
+    if recent.is_empty() {
+        lines.push("변경 기록이 없습니다.".to_owned());
+    }
+    Block::section(Text::mrkdwn(lines.join("\n")))
+}
+
+pub fn format_usage(u: &Usage) -> String {
+    // Registered network or broadcast addresses can push `using` over `size`.
+    let percent = if u.size == 0 {
+        0
+    } else {
+        (u.using * 100 / u.size).min(100)
+    };
+    format!(
+        "`{}` {}{} {}% (사용중 {} / 등록 {} / 전체 {})",
+        u.subnet,
+        "▓".repeat(percent / 10),
+        "░".repeat(10 - percent / 10),
+        percent,
+        u.using,
+        u.registered,
+        u.size
+    )
+}
+
+fn generate_usage_block(usage: &[Usage]) -> Block {
+    let mut lines = vec!["*서브넷 사용률*".to_owned()];
+    lines.extend(usage.iter().map(format_usage));
+    Block::section(Text::mrkdwn(lines.join("\n")))
+}
+
+pub fn generate_home_view(own: &[Entry], recent: &[Entry], usage: &[Usage]) -> View {
+    let actions = vec![
+        Element::Button(Button::new("검색", "search", "search")),
+        Element::Button(Button::new("할당", "allocate", "allocate").primary()),
+        Element::Button(Button::new("등록", "register", "register")),
+        Element::Button(Button::new("담당자 변경", "transfer", "transfer")),
+    ];
+
+    let mut blocks = vec![
+        Block::actions(callback::encode(&Action::Home), actions),
+        Block::actions(
+            callback::encode(&Action::Open),
+            vec![
+                Element::ExternalSelect(ExternalSelect {
+                    action_id: "open_ip".to_owned(),
+                    placeholder: Some(Text::plain("IP 또는 도메인으로 찾기")),
+                    min_query_length: 0,
+                }),
+            ],
+        ),
+        generate_own_block(own),
+        Block::Divider,
+        generate_recent_block(recent),
+    ];
+    if !usage.is_empty() {
+        blocks.push(Block::Divider);
+        blocks.push(generate_usage_block(usage));
+    }
+    View::home(blocks)
+}
+
+pub fn publish(user_id: &str, view: &View, token: &str) -> Result<()> {
+    super::request_api(
+        "views.publish",
+        &json!({
+            "user_id": user_id,
+            "view": view,
+        }),
+        token,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slack::tests::{assert_escaped, hostile_entry};
+
+    #[test]
+    fn usage_over_size_is_full() {
+        let usage = Usage {
+            subnet: "10.0.0.0/30".to_owned(),
+            using: 4,
+            registered: 4,
+            size: 2,
+        };
+        assert_eq!(
+            format_usage(&usage),
+            "`10.0.0.0/30` ▓▓▓▓▓▓▓▓▓▓ 100% (사용중 4 / 등록 4 / 전체 2)"
+        );
+    }
+
+    #[test]
+    fn home_view_escapes_user_text() {
+        let entries = vec![hostile_entry("10.0.0.1")];
+        let view = generate_home_view(&entries, &entries, &[]);
+        assert_escaped(&::slack::serde_json::to_string(&view).unwrap());
+    }
+}
@@ -1,162 +1,936 @@
-extern crate regex;
-extern crate reqwest;
 extern crate serde_json;
 
 use ip::Entry;
+use probe;
+use drift::Drift;
+use discovery::Candidate;
+use command::{Usage, USAGES};
+use history::{Change, Record};
+use page::{self, Cursor, Group, Page};
+use subnet::Subnet;
+use super::callback::{self, Action};
+use super::block::{escape, Block, Button, Confirm, Element, Message, OptionObject, StaticSelect,
+                   Suggestions, Text};
+use super::home::{self, Usage as SubnetUsage};
 
-const IP_MESSAGE: &str = include_str!("json/ip_message.json");
-const CREATE_NEW_MESSAGE: &str = include_str!("json/create_new_message.json");
-const LIST_MESSAGE: &str = include_str!("json/list_message.json");
-const LIST_MESSAGE_WITH_NO_BUTTON: &str = include_str!("json/list_message_with_no_button.json");
-
-fn generate_port_buttons(port: &[u32]) -> String {
-    serde_json::to_string(&port.iter()
+fn generate_port_buttons(ports: &[u32]) -> Vec<Element> {
+    ports
+        .iter()
+        .take(24)
         .map(|port| {
-            json!({
-                "name": "edit_port",
-                "text": format!("{}", port),
-                "type": "button",
-                "value": format!("{}", port)
-            })
+            Element::Button(Button::new(
+                format!("{}", port),
+                &format!("edit_port-{}", port),
+                &format!("{}", port),
+            ))
         })
-        .chain(
-            vec![
-                json!({
-                    "name": "add_port",
-                    "text": "추가",
-                    "type": "button",
-                    "style": "primary",
-                    "value": "add_port"
-            }),
-            ].into_iter(),
+        .chain(Some(Element::Button(
+            Button::new("추가", "add_port", "add_port").primary(),
+        )))
+        .collect()
+}
+
+fn generate_status_blocks(entry: &Entry) -> Vec<Block> {
+    let mut mismatches = probe::mismatches(entry);
+    if entry.domain.is_some() {
+        mismatches.extend(entry.dns_issues.iter().cloned());
+    }
+    let text = if entry.last_probed.is_none() {
+        "응답 확인 기록 없음".to_owned()
+    } else if entry.reachable_ports.is_empty() {
+        if probe::is_responding(entry) {
+            "응답함".to_owned()
+        } else {
+            "응답 없음".to_owned()
+        }
+    } else {
+        format!(
+            "응답 포트: {}",
+            entry
+                .reachable_ports
+                .iter()
+                .map(|port| format!("{}", port))
+                .collect::<Vec<_>>()
+                .join(", ")
         )
-        .collect::<Vec<_>>())
-        .unwrap_or_default()
+    };
+    let text = mismatches
+        .iter()
+        .fold(text, |text, m| format!("{}\n:warning: {}", text, escape(m)));
+    let mut blocks = vec![Block::section(Text::mrkdwn(text))];
+    if let Some(last_seen) = entry.last_seen {
+        blocks.push(Block::context(vec![Text::mrkdwn(format!(
+            "마지막 응답: <!date^{}^{{date_short_pretty}} {{time}}|{}>",
+            last_seen, last_seen
+        ))]));
+    }
+    blocks
 }
 
 pub fn generate_ip_message(entry: &Entry) -> String {
-    lazy_static! {
-        static ref REGEX_INFOS: regex::Regex =
-            regex::Regex::new(r"(?:/(ip|description|domain|using|using_style|ports)/)+?")
-            .unwrap();
+    let callback = Action::Ip {
+        ip: entry.ip.clone(),
+    };
+    let title = match entry.description {
+        Some(ref description) if !description.is_empty() => {
+            format!("*{}*\n{}", entry.ip, escape(description))
+        }
+        _ => format!("*{}*", entry.ip),
+    };
+    let mut blocks = vec![Block::section(Text::mrkdwn(title))];
+    let mut context = vec![];
+    if let Some(ref owner) = entry.owner {
+        context.push(Text::mrkdwn(format!("담당: <@{}>", owner)));
     }
-    REGEX_INFOS
-        .replace_all(IP_MESSAGE, |caps: &regex::Captures| match &caps[1] {
-            "ip" => entry.ip.clone(),
-            "description" => entry.description.clone().unwrap_or_default(),
-            "domain" => entry
-                .domain
-                .clone()
-                .unwrap_or_else(|| "도메인 추가".to_owned()),
-            "using" => if entry.using {
-                "사용중"
-            } else {
-                "미사용"
-            }.to_owned(),
-            "using_style" => if entry.using { "danger" } else { "primary" }.to_owned(),
-            "ports" => generate_port_buttons(&entry.open_ports),
-            _ => String::new(),
-        })
-        .into_owned()
+    if !entry.tags.is_empty() {
+        context.push(Text::mrkdwn(format!("태그: {}", escape(&entry.tags.join(", ")))));
+    }
+    if !context.is_empty() {
+        blocks.push(Block::context(context));
+    }
+    blocks.extend(vec![
+        Block::actions(
+            callback::encode(&callback),
+            vec![
+                Element::Button(Button::new(
+                    entry
+                        .domain
+                        .clone()
+                        .filter(|domain| !domain.is_empty())
+                        .unwrap_or_else(|| "도메인 추가".to_owned()),
+                    "edit_domain",
+                    "edit_domain",
+                )),
+                Element::Button(if entry.using {
+                    Button::new("사용중", "toggle_using", "toggle_using").danger()
+                } else {
+                    Button::new("미사용", "toggle_using", "toggle_using").primary()
+                }),
+            ],
+        ),
+        Block::section(Text::plain("개방 포트")),
+        Block::actions(
+            callback::encode_keyed(&callback, "ports"),
+            generate_port_buttons(&entry.open_ports),
+        ),
+        Block::Divider,
+    ]);
+    blocks.extend(generate_status_blocks(entry));
+    blocks.push(Block::actions(
+        callback::encode_keyed(&callback, "more"),
+        vec![
+            Element::Button(Button::new(
+                "설명 수정",
+                "edit_description",
+                "edit_description",
+            )),
+            Element::Button(Button::new("전체 수정", "edit_entry", "edit_entry")),
+            Element::Button(Button::new("새로고침", "refresh", "refresh")),
+            Element::Button(
+                Button::new("삭제", "delete_entry", "delete_entry")
+                    .danger()
+                    .confirm(Confirm::new(
+                        "삭제하시겠습니까?",
+                        "삭제된 정보는 복구할 수 없습니다.",
+                        "삭제",
+                        "취소",
+                    )),
+            ),
+        ],
+    ));
+    Message::new(entry.ip.clone(), blocks).to_json()
+}
+
+/// `part` is set when the blocks are one of several cards in a message.
+fn encode_action(callback: &Action, part: bool) -> String {
+    if part {
+        callback::encode_part(callback)
+    } else {
+        callback::encode(callback)
+    }
+}
+
+fn generate_compact_ip_blocks(entry: &Entry, part: bool) -> Vec<Block> {
+    vec![
+        Block::section(Text::mrkdwn(format!("*{}*", entry.ip))),
+        Block::fields(vec![
+            Text::mrkdwn(format!(
+                "*도메인*\n{}",
+                entry
+                    .domain
+                    .as_ref()
+                    .map(|domain| escape(domain))
+                    .unwrap_or_else(|| "-".to_owned())
+            )),
+            Text::mrkdwn(format!(
+                "*상태*\n{}",
+                if entry.using { "사용중" } else { "미사용" }
+            )),
+            Text::mrkdwn(format!(
+                "*담당*\n{}",
+                entry
+                    .owner
+                    .as_ref()
+                    .map(|owner| format!("<@{}>", owner))
+                    .unwrap_or_else(|| "-".to_owned())
+            )),
+        ]),
+        Block::actions(
+            encode_action(
+                &Action::Ip {
+                    ip: entry.ip.clone(),
+                },
+                part,
+            ),
+            vec![Element::Button(Button::new("자세히", "refresh", "refresh"))],
+        ),
+    ]
+}
+
+pub fn generate_compact_ip_message(entry: &Entry) -> String {
+    Message::new(entry.ip.clone(), generate_compact_ip_blocks(entry, false)).to_json()
+}
+
+fn generate_create_new_blocks(ip: &str, part: bool) -> Vec<Block> {
+    vec![
+        Block::section(Text::plain(format!(
+            "{}를 찾을 수 없습니다. 새로 생성하시겠습니까?",
+            ip
+        ))),
+        Block::actions(
+            encode_action(&Action::CreateNew { ip: ip.to_owned() }, part),
+            vec![
+                Element::Button(
+                    Button::new("생성", "create_new_entry", "create_new_entry").primary(),
+                ),
+                Element::Button(Button::new("취소", "create_cancel", "create_cancel")),
+            ],
+        ),
+    ]
 }
 
 pub fn generate_create_new_message(ip: &str) -> String {
-    CREATE_NEW_MESSAGE.replace("/ip/", ip)
+    Message::new(
+        format!("{}를 찾을 수 없습니다.", ip),
+        generate_create_new_blocks(ip, false),
+    ).to_json()
+}
+
+/// At most this many addresses fit into the 50 blocks of a lookup message.
+const LOOKUP_MAX: usize = 11;
+
+/// One card per address found in a message; `None` for addresses which are not registered.
+pub fn generate_lookup_message(found: &[(String, Option<Entry>)]) -> String {
+    let text = format!("메시지에서 IP {}개를 찾았습니다.", found.len());
+    let mut blocks = vec![Block::section(Text::plain(text.clone()))];
+    for &(ref ip, ref entry) in found.iter().take(LOOKUP_MAX) {
+        blocks.push(Block::Divider);
+        blocks.extend(match *entry {
+            Some(ref entry) => generate_compact_ip_blocks(entry, true),
+            None => generate_create_new_blocks(ip, true),
+        });
+    }
+    if found.len() > LOOKUP_MAX {
+        blocks.push(Block::context(vec![
+            Text::plain(format!(
+                "외 {}개: {}",
+                found.len() - LOOKUP_MAX,
+                found[LOOKUP_MAX..]
+                    .iter()
+                    .map(|&(ref ip, _)| ip.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        ]));
+    }
+    Message::new(text, blocks).to_json()
 }
 
-fn generate_list_fields(entries: &[Entry], page: usize) -> (String, usize) {
-    let v = entries
+pub fn generate_list_fields(entries: &[Entry]) -> Vec<Text> {
+    entries
         .iter()
-        .skip(page * 8)
-        .take(8)
         .map(|entry| {
-            json!({
-                "title": entry.ip,
-                "value":
-                    entry.domain.as_ref()
-                        .map(|s| format!("{}\n", s))
-                        .unwrap_or_default() +
-                    &entry.description.as_ref()
-                        .map(|s| format!("{}\n", s))
-                        .unwrap_or_default() +
-                    if entry.using { "사용중" } else { "미사용" },
-                "short": true
-            })
+            Text::mrkdwn(
+                format!("*{}*\n", entry.ip)
+                    + &entry
+                        .domain
+                        .as_ref()
+                        .map(|s| format!("{}\n", escape(s)))
+                        .unwrap_or_default()
+                    + &entry
+                        .description
+                        .as_ref()
+                        .map(|s| format!("{}\n", escape(s)))
+                        .unwrap_or_default() + if entry.using {
+                    "사용중"
+                } else {
+                    "미사용"
+                },
+            )
+        })
+        .collect()
+}
+
+fn generate_page_button(text: &str, action_id: &str, page: &Page, cursor: Cursor) -> Element {
+    Element::Button(Button::new(
+        text,
+        action_id,
+        &page.with_cursor(cursor).to_string(),
+    ))
+}
+
+/// Slack rejects option values longer than this.
+const OPTION_VALUE_MAX: usize = 150;
+
+/// Left out when the page no longer fits into an option value, e.g. for a long query.
+fn generate_page_select(page: &Page, start: usize, len: usize, size: usize) -> Option<Element> {
+    let count = (len + size - 1) / size;
+    let options = (0..count.min(100))
+        .map(|n| {
+            OptionObject::new(
+                format!("{} / {} 페이지", n + 1, count),
+                &page.with_cursor(Cursor::Page(n)).to_string(),
+            )
         })
         .collect::<Vec<_>>();
-    (serde_json::to_string(&v).unwrap_or_default(), v.len())
+    if options
+        .iter()
+        .any(|option| option.value.len() > OPTION_VALUE_MAX)
+    {
+        return None;
+    }
+    let initial_option = if start % size == 0 {
+        options.get(start / size).cloned()
+    } else {
+        None
+    };
+    Some(Element::StaticSelect(StaticSelect {
+        action_id: "page_jump".to_owned(),
+        placeholder: Some(Text::plain("페이지 이동")),
+        options,
+        initial_option,
+    }))
 }
 
-pub fn generate_list_message(entries: &[Entry], page: usize) -> String {
-    lazy_static! {
-        static ref REGEX_LIST_INFOS: regex::Regex =
-            regex::Regex::new(r"(?:/(title|text|fields|callback|value)/)+?")
-            .unwrap();
+pub fn generate_entry_option(entry: &Entry) -> OptionObject {
+    match entry.domain {
+        Some(ref domain) if !domain.is_empty() => {
+            OptionObject::new(format!("{} ({})", entry.ip, domain), &entry.ip)
+        }
+        _ => OptionObject::new(entry.ip.clone(), &entry.ip),
     }
-    let (fields, len_fields) = generate_list_fields(entries, page);
-    REGEX_LIST_INFOS
-        .replace_all(
-            if entries.len() > (page + 1) * 8 {
-                LIST_MESSAGE
-            } else {
-                LIST_MESSAGE_WITH_NO_BUTTON
+}
+
+pub fn generate_entry_options(entries: &[Entry]) -> String {
+    Suggestions {
+        options: entries.iter().take(100).map(generate_entry_option).collect(),
+    }.to_json()
+}
+
+pub fn generate_open_select(shown: &[Entry]) -> Block {
+    Block::actions(
+        callback::encode(&Action::Open),
+        vec![
+            Element::StaticSelect(StaticSelect {
+                action_id: "open_ip".to_owned(),
+                placeholder: Some(Text::plain("IP 카드 열기")),
+                options: shown.iter().map(generate_entry_option).collect(),
+                initial_option: None,
+            }),
+        ],
+    )
+}
+
+fn generate_grouped_blocks(
+    shown: &[Entry],
+    group: Option<Group>,
+    subnets: &[Subnet],
+) -> Vec<Block> {
+    let group = match group {
+        Some(group) => group,
+        None => return vec![Block::fields(generate_list_fields(shown))],
+    };
+    let mut blocks = vec![];
+    let mut rest = shown;
+    while let Some(first) = rest.first() {
+        let label = page::label(first, group, subnets);
+        let len = rest.iter()
+            .take_while(|entry| page::label(entry, group, subnets) == label)
+            .count();
+        blocks.push(Block::section(Text::mrkdwn(format!("*{}*", escape(&label)))));
+        blocks.push(Block::fields(generate_list_fields(&rest[..len])));
+        rest = &rest[len..];
+    }
+    blocks
+}
+
+/// `entries` must already be arranged with `page::arrange` for `page.options`.
+pub fn generate_list_message(
+    entries: &[Entry],
+    page: &Page,
+    subnets: &[Subnet],
+    size: usize,
+) -> String {
+    let (title, callback) = match page.query {
+        Some(ref query) => (format!("{} 검색 결과", query), Action::Query),
+        None => ("IP 목록".to_owned(), Action::List),
+    };
+    let (start, shown) = page::slice(entries, &page.cursor, size);
+    let mut blocks = vec![
+        Block::section(Text::mrkdwn(format!(
+            "*{}*\n{}-{} / {}",
+            escape(&title),
+            if shown.is_empty() { 0 } else { start + 1 },
+            start + shown.len(),
+            entries.len()
+        ))),
+    ];
+    if !shown.is_empty() {
+        blocks.extend(generate_grouped_blocks(shown, page.options.group, subnets));
+        blocks.push(generate_open_select(shown));
+    }
+    let mut controls = vec![];
+    if let (Some(first), true) = (shown.first(), start > 0) {
+        controls.push(generate_page_button("처음", "page_first", page, Cursor::First));
+        controls.push(generate_page_button(
+            "이전",
+            "page_prev",
+            page,
+            Cursor::Before {
+                ip: first.ip.clone(),
+                offset: start,
             },
-            |caps: &regex::Captures| match &caps[1] {
-                "title" => "IP 목록".to_owned(),
-                "text" => format!(
-                    "{}-{} / {}",
-                    page * 8 + 1,
-                    page * 8 + len_fields,
-                    entries.len()
-                ),
-                "fields" => fields.clone(),
-                "callback" => "list-list".to_owned(),
-                "value" => format!("{}", page),
-                _ => String::new(),
+        ));
+    }
+    if let (Some(last), true) = (shown.last(), start + shown.len() < entries.len()) {
+        controls.push(generate_page_button(
+            "다음",
+            "page_next",
+            page,
+            Cursor::After {
+                ip: last.ip.clone(),
+                offset: start + shown.len(),
             },
-        )
-        .into_owned()
+        ));
+        controls.push(generate_page_button("마지막", "page_last", page, Cursor::Last));
+    }
+    if entries.len() > size {
+        controls.extend(generate_page_select(page, start, entries.len(), size));
+    }
+    if page.query.is_some() && !entries.is_empty() {
+        controls.push(Element::Button(Button::new(
+            "일괄 작업",
+            "bulk",
+            &page.to_string(),
+        )));
+    }
+    if !controls.is_empty() {
+        blocks.push(Block::actions(callback::encode(&callback), controls));
+    }
+    Message::new(title, blocks).to_json()
 }
 
-pub fn generate_query_message(query: &str, entries: &[Entry], page: usize) -> String {
-    lazy_static! {
-        static ref REGEX_QUERY_INFOS: regex::Regex =
-            regex::Regex::new(r"(?:/(title|text|fields|callback|value)/)+?")
-            .unwrap();
+pub fn generate_drift_message(ip: &str, drift: &Drift) -> String {
+    if drift.is_empty() {
+        return Message::text(format!("{} 포트 변화가 모두 처리되었습니다.", ip)).to_json();
     }
-    let (fields, len_fields) = generate_list_fields(entries, page);
-    REGEX_QUERY_INFOS
-        .replace_all(
-            if entries.len() > (page + 1) * 8 {
-                LIST_MESSAGE
-            } else {
-                LIST_MESSAGE_WITH_NO_BUTTON
-            },
-            |caps: &regex::Captures| match &caps[1] {
-                "title" => format!("{} 검색 결과", query),
-                "text" => format!(
-                    "{}-{} / {}",
-                    page * 8 + 1,
-                    page * 8 + len_fields,
-                    entries.len()
+    let callback = Action::Drift { ip: ip.to_owned() };
+    let mut blocks = vec![
+        Block::section(Text::plain(format!("{} 포트 변화가 감지되었습니다.", ip))),
+    ];
+    for port in &drift.undeclared {
+        blocks.push(Block::section(Text::plain(format!(
+            "포트 {}: 목록에 없지만 열려 있습니다",
+            port
+        ))));
+        blocks.push(Block::actions(
+            callback::encode_keyed(&callback, &format!("{}", port)),
+            vec![
+                Element::Button(
+                    Button::new("목록에 추가", "drift_add", &format!("{}", port)).primary(),
                 ),
-                "fields" => fields.clone(),
-                "callback" => format!("query-{}", query),
-                "value" => format!("{}", page),
-                _ => String::new(),
-            },
+                Element::Button(Button::new("무시", "drift_ignore", &format!("{}", port))),
+            ],
+        ));
+    }
+    for port in &drift.closed {
+        blocks.push(Block::section(Text::plain(format!(
+            "포트 {}: 목록에 있지만 닫혀 있습니다",
+            port
+        ))));
+        blocks.push(Block::actions(
+            callback::encode_keyed(&callback, &format!("{}", port)),
+            vec![
+                Element::Button(Button::new("무시", "drift_ignore", &format!("{}", port))),
+            ],
+        ));
+    }
+    Message::new(format!("{} 포트 변화가 감지되었습니다.", ip), blocks).to_json()
+}
+
+pub fn generate_discovery_message(candidates: &[Candidate]) -> String {
+    let shown = candidates.iter().take(15).collect::<Vec<_>>();
+    let text = if candidates.len() > shown.len() {
+        format!(
+            "등록되지 않은 IP {}개가 응답합니다. (외 {}개)",
+            candidates.len(),
+            candidates.len() - shown.len()
         )
-        .into_owned()
+    } else {
+        format!("등록되지 않은 IP {}개가 응답합니다.", candidates.len())
+    };
+    let mut blocks = vec![Block::section(Text::plain(text.clone()))];
+    for c in shown {
+        blocks.push(Block::Divider);
+        blocks.extend(generate_create_new_blocks(&c.ip, true));
+    }
+    Message::new(text, blocks).to_json()
+}
+
+pub fn generate_dns_report_message(entries: &[Entry]) -> String {
+    let text = format!("DNS 불일치 {}건", entries.len());
+    let mut blocks = vec![Block::section(Text::plain(text.clone()))];
+    for entry in entries.iter().take(40) {
+        blocks.push(Block::section(Text::mrkdwn(format!(
+            "*{} ({})*\n{}",
+            entry.ip,
+            escape(&entry.domain.clone().unwrap_or_default()),
+            escape(&entry.dns_issues.join("\n"))
+        ))));
+    }
+    Message::new(text, blocks).to_json()
+}
+
+fn format_usage(usage: &Usage) -> String {
+    format!("`{}`\n{}", escape(usage.synopsis), usage.description)
+}
+
+pub fn generate_help_message(topic: Option<&str>) -> String {
+    let text = match USAGES.iter().find(|usage| Some(usage.name) == topic) {
+        Some(usage) => format_usage(usage),
+        None => USAGES
+            .iter()
+            .map(format_usage)
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+    };
+    Message::new("도움말", vec![Block::section(Text::mrkdwn(text))])
+        .ephemeral()
+        .to_json()
+}
+
+pub fn generate_usage_error_message(name: &str) -> String {
+    let text = USAGES
+        .iter()
+        .find(|usage| usage.name == name)
+        .map(|usage| format!("사용법: `{}`", usage.synopsis))
+        .unwrap_or_else(|| "잘못된 명령입니다.".to_owned());
+    Message::new(&text, vec![Block::section(Text::mrkdwn(escape(&text)))])
+        .ephemeral()
+        .to_json()
+}
+
+fn format_value(field: &str, value: &Option<String>) -> String {
+    match *value {
+        Some(ref value) if field == "owner" => format!("<@{}>", value),
+        Some(ref value) => escape(value),
+        None => "(없음)".to_owned(),
+    }
+}
+
+fn format_change(change: &Change) -> String {
+    let field = match change.field.as_str() {
+        "domain" => "도메인",
+        "using" => "상태",
+        "open_ports" => "개방 포트",
+        "description" => "설명",
+        "owner" => "담당자",
+        "tags" => "태그",
+        other => other,
+    };
+    format!(
+        "{}: {} → {}",
+        field,
+        format_value(&change.field, &change.old),
+        format_value(&change.field, &change.new)
+    )
+}
+
+fn format_action(action: &str) -> &'static str {
+    match action {
+        "create" => "생성",
+        "delete" => "삭제",
+        _ => "수정",
+    }
+}
+
+pub fn generate_change_message(record: &Record) -> String {
+    let action = format_action(&record.action);
+    let mut lines = vec![format!("<@{}> `{}` {}", record.user, record.ip, action)];
+    lines.extend(record.changes.iter().map(format_change));
+    Message::new(
+        format!("{} {}", record.ip, action),
+        vec![Block::section(Text::mrkdwn(lines.join("\n")))],
+    ).to_json()
+}
+
+/// At most this many addresses are named in a summary of a bulk change.
+const SUMMARY_MAX: usize = 40;
+
+/// One notification for changes committed together, which are all made by the same user.
+pub fn generate_changes_message(records: &[Record]) -> String {
+    let user = records.first().map(|record| record.user.as_str()).unwrap_or_default();
+    let mut lines = vec![format!("<@{}> IP {}개 변경", user, records.len())];
+    lines.extend(
+        records
+            .iter()
+            .take(SUMMARY_MAX)
+            .map(|record| format!("`{}` {}", record.ip, format_action(&record.action))),
+    );
+    if records.len() > SUMMARY_MAX {
+        lines.push(format!("외 {}개", records.len() - SUMMARY_MAX));
+    }
+    Message::new(
+        format!("IP {}개 변경", records.len()),
+        vec![Block::section(Text::mrkdwn(lines.join("\n")))],
+    ).to_json()
+}
+
+pub fn generate_history_message(ip: &str, records: &[Record]) -> String {
+    let title = format!("{} 변경 기록", ip);
+    let mut blocks = vec![Block::section(Text::mrkdwn(format!("*{}*", title)))];
+    if records.is_empty() {
+        blocks.push(Block::section(Text::plain("변경 기록이 없습니다.")));
+    }
+    for record in records.iter().rev().take(20) {
+        let action = match record.action.as_str() {
+            "create" => "생성",
+            "delete" => "삭제",
+            "update" => "수정",
+            "denied" => "거부됨",
+            other => other,
+        };
+        let mut lines = vec![format!(
+            "<!date^{}^{{date_short_pretty}} {{time}}|{}> <@{}> {}{}",
+            record.time,
+            record.time,
+            record.user,
+            action,
+            record
+                .attempted
+                .as_ref()
+                .map(|attempted| format!(" ({})", escape(attempted)))
+                .unwrap_or_default()
+        )];
+        lines.extend(record.changes.iter().map(format_change));
+        blocks.push(Block::section(Text::mrkdwn(lines.join("\n"))));
+    }
+    Message::new(title, blocks).to_json()
+}
+
+pub fn generate_subnet_message(usage: &[SubnetUsage], free: &[String]) -> String {
+    let mut blocks = vec![
+        Block::section(Text::mrkdwn(if usage.is_empty() {
+            "등록된 서브넷이 없습니다.".to_owned()
+        } else {
+            format!(
+                "*서브넷 사용률*\n{}",
+                usage
+                    .iter()
+                    .map(home::format_usage)
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            )
+        })),
+    ];
+    if !free.is_empty() {
+        blocks.push(Block::section(Text::mrkdwn(format!(
+            "*빈 IP*\n{}",
+            free.iter()
+                .map(|ip| format!("`{}`", ip))
+                .collect::<Vec<_>>()
+                .join(" ")
+        ))));
+    }
+    Message::new("서브넷 사용률", blocks).to_json()
+}
+
+pub fn generate_export_message(content: &str) -> String {
+    Message::new(
+        "내보내기",
+        vec![
+            Block::section(Text::mrkdwn(format!(
+                "```{}```",
+                Text::plain(escape(if content.is_empty() { "(없음)" } else { content }))
+                    .truncate(2990)
+                    .text
+            ))),
+        ],
+    ).to_json()
+}
+
+pub fn generate_already_exists_message(ip: &str) -> String {
+    Message::text(format!("{}는 이미 등록된 IP입니다.", ip))
+        .ephemeral()
+        .to_json()
+}
+
+pub fn generate_denied_message() -> String {
+    Message::text("죄송합니다. 이 작업은 담당자나 관리자만 할 수 있습니다.")
+        .ephemeral()
+        .to_json()
+}
+
+/// Messages which already carry a `response_type` (errors, denials) are left as they are.
+/// `key` names the copy of the message which the share button posts.
+pub fn with_visibility(message: &str, share: bool, key: &str) -> String {
+    let mut payload: serde_json::Value = match serde_json::from_str(message) {
+        Ok(payload) => payload,
+        Err(_) => return message.to_owned(),
+    };
+    if payload.get("response_type").is_some() {
+        return message.to_owned();
+    }
+    if share {
+        payload["response_type"] = json!("in_channel");
+    } else {
+        payload["response_type"] = json!("ephemeral");
+        if let Some(blocks) = payload["blocks"].as_array_mut() {
+            blocks.push(json!(Block::actions(
+                callback::encode(&Action::Share {
+                    key: key.to_owned(),
+                }),
+                vec![Element::Button(Button::new("채널에 공유", "share", ""))],
+            )));
+        }
+    }
+    payload.to_string()
+}
+
+pub fn generate_shared_message(message: &str) -> String {
+    let mut payload: serde_json::Value = match serde_json::from_str(message) {
+        Ok(payload) => payload,
+        Err(_) => return message.to_owned(),
+    };
+    if payload.get("response_type").is_none() {
+        payload["response_type"] = json!("in_channel");
+    }
+    payload["replace_original"] = json!(false);
+    payload.to_string()
+}
+
+pub fn generate_expired_message() -> String {
+    let mut payload = json!(Message::text("만료된 메시지입니다. 명령을 다시 실행해 주세요.").ephemeral());
+    payload["replace_original"] = json!(false);
+    payload.to_string()
 }
 
 pub fn generate_cancelled_message() -> String {
-    "{ \"text\": \"취소되었습니다.\" }".to_owned()
+    Message::text("취소되었습니다.").to_json()
 }
 
 pub fn generate_deleted_message() -> String {
-    "{ \"text\": \"삭제되었습니다.\" }".to_owned()
+    Message::text("삭제되었습니다.").to_json()
+}
+
+pub fn generate_no_ip_message() -> String {
+    Message::text("메시지에서 IP를 찾지 못했습니다.")
+        .ephemeral()
+        .to_json()
 }
 
 pub fn generate_inexist_message() -> String {
-    "{ \"text\": \"존재하지 않는 IP 입니다.\" }".to_owned()
+    Message::text("존재하지 않는 IP 입니다.").ephemeral().to_json()
+}
+
+pub fn generate_error_message() -> String {
+    Message::text("요청을 처리하지 못했습니다. 잠시 후 다시 시도해 주세요.")
+        .ephemeral()
+        .to_json()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use page::{Options, Sort};
+    use slack::tests::{assert_escaped, hostile_entry, HOSTILE};
+
+    #[test]
+    fn ip_message_escapes_user_text() {
+        assert_escaped(&generate_ip_message(&hostile_entry("10.0.0.1")));
+        assert_escaped(&generate_compact_ip_message(&hostile_entry("10.0.0.1")));
+    }
+
+    #[test]
+    fn list_message_escapes_user_text() {
+        let entries = vec![hostile_entry("10.0.0.1"), hostile_entry("10.0.0.2")];
+        let subnets = vec![Subnet::from_str("10.0.0.0/24").unwrap()];
+        let page = Page::new(Some(HOSTILE.to_owned()), Options::default());
+        assert_escaped(&generate_list_message(&entries, &page, &subnets, 8));
+        let page = Page::new(
+            Some(HOSTILE.to_owned()),
+            Options {
+                sort: Sort::Domain,
+                group: Some(Group::Tag),
+                size: None,
+            },
+        );
+        assert_escaped(&generate_list_message(&entries, &page, &subnets, 8));
+    }
+
+    #[test]
+    fn history_and_change_messages_escape_user_text() {
+        let record = Record {
+            time: 0,
+            ip: "10.0.0.1".to_owned(),
+            user: "U0USER".to_owned(),
+            action: "denied".to_owned(),
+            changes: ::history::diff(None, Some(&hostile_entry("10.0.0.1"))),
+            attempted: Some(HOSTILE.to_owned()),
+        };
+        assert_escaped(&generate_change_message(&record));
+        assert_escaped(&generate_history_message("10.0.0.1", &[record]));
+    }
+
+    #[test]
+    fn share_button_names_the_kept_message() {
+        let message = generate_ip_message(&hostile_entry("10.0.0.1"));
+        let shown: serde_json::Value =
+            serde_json::from_str(&with_visibility(&message, false, "k1")).unwrap();
+        assert_eq!(shown["response_type"], "ephemeral");
+        let share = shown["blocks"].as_array().unwrap().last().unwrap().clone();
+        match callback::decode(share["block_id"].as_str().unwrap()) {
+            Some(Action::Share { key }) => assert_eq!(key, "k1"),
+            _ => panic!("expected a share callback"),
+        }
+
+        let shared: serde_json::Value =
+            serde_json::from_str(&generate_shared_message(&message)).unwrap();
+        assert_eq!(shared["response_type"], "in_channel");
+        assert_eq!(shared["replace_original"], false);
+    }
+
+    #[test]
+    fn long_lists_get_a_page_select() {
+        let entries = (1..12)
+            .map(|i| hostile_entry(&format!("10.0.0.{}", i)))
+            .collect::<Vec<_>>();
+        let page = Page::new(None, Options::default()).with_cursor(Cursor::Page(1));
+        let message: serde_json::Value =
+            serde_json::from_str(&generate_list_message(&entries, &page, &[], 5)).unwrap();
+        let controls = message["blocks"].as_array().unwrap().last().unwrap().clone();
+        let select = controls["elements"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|element| element["action_id"] == "page_jump")
+            .cloned()
+            .unwrap();
+        assert_eq!(select["options"].as_array().unwrap().len(), 3);
+        assert_eq!(select["initial_option"]["text"]["text"], "2 / 3 페이지");
+        let jump = Page::from_str(select["options"][2]["value"].as_str().unwrap()).unwrap();
+        match jump.cursor {
+            Cursor::Page(2) => {}
+            _ => panic!("expected the third page"),
+        }
+    }
+
+    #[test]
+    fn lookup_names_every_address() {
+        let found = (1..15)
+            .map(|i| {
+                let ip = format!("10.0.0.{}", i);
+                let entry = if i % 2 == 0 { Some(hostile_entry(&ip)) } else { None };
+                (ip, entry)
+            })
+            .collect::<Vec<_>>();
+        let json = generate_lookup_message(&found);
+        assert_escaped(&json);
+        let message: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert!(message["blocks"].as_array().unwrap().len() <= 49);
+        for &(ref ip, _) in &found {
+            assert!(json.contains(ip.as_str()), "{} is missing", ip);
+        }
+    }
+
+    #[test]
+    fn lookup_cards_answer_separately() {
+        let found = vec![
+            ("10.0.0.1".to_owned(), Some(hostile_entry("10.0.0.1"))),
+            ("10.0.0.2".to_owned(), None),
+        ];
+        let message: serde_json::Value =
+            serde_json::from_str(&generate_lookup_message(&found)).unwrap();
+        let actions = message["blocks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|block| block["type"] == "actions")
+            .map(|block| block["block_id"].as_str().unwrap().to_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(actions.len(), 2);
+        assert!(actions.iter().all(|block_id| callback::is_part(block_id)));
+    }
+
+    #[test]
+    fn discovery_cards_answer_separately() {
+        let candidates = (1..4)
+            .map(|i| Candidate {
+                ip: format!("10.0.0.{}", i),
+                first_seen: 0,
+                last_seen: 0,
+            })
+            .collect::<Vec<_>>();
+        let message: serde_json::Value =
+            serde_json::from_str(&generate_discovery_message(&candidates)).unwrap();
+        let actions = message["blocks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|block| block["type"] == "actions")
+            .map(|block| block["block_id"].as_str().unwrap().to_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(actions.len(), 3);
+        assert!(actions.iter().all(|block_id| callback::is_part(block_id)));
+    }
+
+    #[test]
+    fn report_messages_escape_user_text() {
+        let mut entries = vec![hostile_entry("10.0.0.1"), hostile_entry("10.0.0.2")];
+        for entry in &mut entries {
+            entry.dns_issues = vec![HOSTILE.to_owned()];
+        }
+        assert_escaped(&generate_dns_report_message(&entries));
+        let drift = Drift {
+            undeclared: vec![8080],
+            closed: vec![22],
+        };
+        assert_escaped(&generate_drift_message(HOSTILE, &drift));
+        let handled = Drift {
+            undeclared: vec![],
+            closed: vec![],
+        };
+        assert_escaped(&generate_drift_message(HOSTILE, &handled));
+    }
+
+    #[test]
+    fn bulk_changes_are_summarized() {
+        let records = (1..50)
+            .map(|i| Record {
+                time: 0,
+                ip: format!("10.0.0.{}", i),
+                user: "U0USER".to_owned(),
+                action: "delete".to_owned(),
+                changes: vec![],
+                attempted: None,
+            })
+            .collect::<Vec<_>>();
+        let message: serde_json::Value =
+            serde_json::from_str(&generate_changes_message(&records)).unwrap();
+        let text = message["blocks"][0]["text"]["text"].as_str().unwrap();
+        assert!(text.starts_with("<@U0USER> IP 49개 변경"));
+        assert!(text.contains("`10.0.0.40` 삭제"));
+        assert!(!text.contains("`10.0.0.41`"));
+        assert!(text.ends_with("외 9개"));
+    }
 }
@@ -1,9 +1,15 @@
 extern crate reqwest;
 extern crate serde;
+extern crate serde_json;
 
+pub mod block;
+pub mod callback;
+pub mod event;
+pub mod home;
 pub mod message;
-pub mod dialog;
+pub mod modal;
 pub mod submission;
+pub mod signature;
 
 pub mod slash_command {
     extern crate serde_urlencoded;
@@ -12,7 +18,7 @@ pub mod slash_command {
 
     #[derive(Deserialize, Debug)]
     pub struct Request {
-        pub token: String,
+        #[serde(default)] pub token: String,
         pub team_id: String,
         pub team_domain: String,
         pub channel_id: String,
@@ -41,19 +47,131 @@ pub mod slash_command {
 
 use errors::Result;
 
-fn request_api<T>(api: &str, payload: T, token: &str) -> Result<()>
+fn request<T>(url: &str, payload: T, token: Option<&str>) -> Result<()>
 where
     T: serde::ser::Serialize,
 {
     use self::reqwest::header::{qitem, AcceptCharset, Authorization, Charset, Headers};
 
     let mut headers = Headers::new();
-    headers.set(Authorization(format!("Bearer {}", token)));
+    if let Some(token) = token {
+        headers.set(Authorization(format!("Bearer {}", token)));
+    }
     headers.set(AcceptCharset(vec![qitem(Charset::Ext("utf-8".to_owned()))]));
     reqwest::Client::new()
-        .post(&format!("https://slack.com/api/{}", api))
+        .post(url)
         .headers(headers)
         .json(&payload)
         .send()?;
     Ok(())
 }
+
+fn request_api<T>(api: &str, payload: T, token: &str) -> Result<()>
+where
+    T: serde::ser::Serialize,
+{
+    request(&format!("https://slack.com/api/{}", api), payload, Some(token))
+}
+
+pub fn post_message(channel: &str, message: &str, token: &str) -> Result<()> {
+    let mut payload: serde_json::Value = serde_json::from_str(message)?;
+    payload["channel"] = json!(channel);
+    request_api("chat.postMessage", payload, token)
+}
+
+pub fn post_reply(channel: &str, thread_ts: &str, message: &str, token: &str) -> Result<()> {
+    let mut payload: serde_json::Value = serde_json::from_str(message)?;
+    payload["channel"] = json!(channel);
+    payload["thread_ts"] = json!(thread_ts);
+    request_api("chat.postMessage", payload, token)
+}
+
+pub fn usergroup_members(usergroup: &str, token: &str) -> Result<Vec<String>> {
+    #[derive(Deserialize)]
+    struct Members {
+        ok: bool,
+        #[serde(default)] error: String,
+        #[serde(default)] users: Vec<String>,
+    }
+
+    let members: Members = reqwest::Client::new()
+        .post("https://slack.com/api/usergroups.users.list")
+        .form(&[("token", token), ("usergroup", usergroup)])
+        .send()?
+        .json()?;
+    if !members.ok {
+        bail!("usergroups.users.list failed: {}", members.error);
+    }
+    Ok(members.users)
+}
+
+pub fn respond(response_url: &str, message: &str, replace_original: bool) -> Result<()> {
+    let mut payload: serde_json::Value = serde_json::from_str(message)?;
+    if payload.get("replace_original").is_none() {
+        payload["replace_original"] = json!(replace_original);
+    }
+    request(response_url, payload, None)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::serde_json::{self, Value};
+    use std::path::Path;
+    use ip::Entry;
+
+    /// Text a user could put in any free-form field.
+    pub const HOSTILE: &str = "<!channel> & \"quoted\" > <http://evil.example|click>\nsecond line";
+
+    pub fn hostile_entry(ip: &str) -> Entry {
+        let mut entry = Entry::draft(ip, Path::new("."));
+        entry.domain = Some(HOSTILE.to_owned());
+        entry.description = Some(HOSTILE.to_owned());
+        entry.tags = vec![HOSTILE.to_owned()];
+        entry.owner = Some("U0OWNER".to_owned());
+        entry
+    }
+
+    fn texts<'a>(value: &'a Value, mrkdwn: &mut Vec<&'a str>, plain: &mut Vec<&'a str>) {
+        match *value {
+            Value::Object(ref object) => {
+                match (object.get("type").and_then(Value::as_str), object.get("text")) {
+                    (Some("plain_text"), Some(&Value::String(ref text))) => plain.push(text),
+                    // Top level `text` of a message is rendered as mrkdwn as well.
+                    (Some("mrkdwn"), Some(&Value::String(ref text)))
+                    | (None, Some(&Value::String(ref text))) => mrkdwn.push(text),
+                    _ => (),
+                }
+                for (key, value) in object {
+                    if key == "initial_value" {
+                        plain.extend(value.as_str());
+                    }
+                    texts(value, mrkdwn, plain);
+                }
+            }
+            Value::Array(ref array) => for value in array {
+                texts(value, mrkdwn, plain);
+            },
+            _ => (),
+        }
+    }
+
+    /// Asserts that `HOSTILE` shows up in `json`, escaped wherever it is read as mrkdwn and
+    /// untouched in plain text, which Slack shows as it is.
+    pub fn assert_escaped(json: &str) {
+        let value = serde_json::from_str::<Value>(json).expect("invalid JSON");
+        let (mut mrkdwn, mut plain) = (vec![], vec![]);
+        texts(&value, &mut mrkdwn, &mut plain);
+        let mut shown = false;
+        for text in mrkdwn {
+            assert!(!text.contains("<!channel>"), "unescaped mention in {:?}", text);
+            assert!(!text.contains("<http"), "unescaped link in {:?}", text);
+            assert!(!text.contains(" & "), "unescaped ampersand in {:?}", text);
+            shown |= text.contains("&lt;!channel&gt; &amp; \"quoted\" &gt;");
+        }
+        for text in plain {
+            assert!(!text.contains("&lt;"), "escaped plain text {:?}", text);
+            shown |= text.contains("<!channel>");
+        }
+        assert!(shown, "hostile text was not rendered in {}", json);
+    }
+}
@@ -0,0 +1,600 @@
+extern crate serde_json;
+
+use ip::Entry;
+use errors::Result;
+use bulk::{Bulk, Operation};
+use page::Page;
+use super::block::{escape, Block, Checkboxes, Element, ExternalSelect, OptionObject,
+                   PlainTextInput, StaticSelect, Text, UsersSelect, View};
+use super::message;
+use super::callback;
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct Metadata {
+    #[serde(skip_serializing_if = "Option::is_none")] pub response_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")] pub port: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")] pub page: Option<Page>,
+    #[serde(skip_serializing_if = "Option::is_none")] pub bulk: Option<Bulk>,
+}
+
+impl Metadata {
+    pub fn from_str(s: &str) -> Self {
+        serde_json::from_str(s).unwrap_or_default()
+    }
+}
+
+fn show(view: View, metadata: &Metadata, trigger_id: &str, token: &str) -> Result<()> {
+    super::request_api(
+        "views.open",
+        &json!({
+            "view": view.private_metadata(serde_json::to_string(metadata)?),
+            "trigger_id": trigger_id,
+        }),
+        token,
+    )
+}
+
+fn metadata(response_url: Option<&str>) -> Metadata {
+    Metadata {
+        response_url: response_url.map(str::to_owned),
+        ..Metadata::default()
+    }
+}
+
+fn text_input(block_id: &str, label: &str, value: Option<String>, optional: bool) -> Block {
+    Block::input(
+        block_id,
+        label,
+        Element::PlainTextInput(PlainTextInput::new(block_id, value)),
+        optional,
+    )
+}
+
+fn format_ports(ports: &[u32]) -> String {
+    ports
+        .iter()
+        .map(|port| format!("{}", port))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn edit_domain_view(entry: &Entry) -> View {
+    View::modal(
+        callback::encode(&callback::View::EditDomain { ip: entry.ip.clone() }),
+        "도메인 추가/수정",
+        vec![text_input("domain", "도메인", entry.domain.clone(), true)],
+    )
+}
+
+pub fn show_edit_domain_modal(
+    entry: &Entry,
+    trigger_id: &str,
+    response_url: Option<&str>,
+    token: &str,
+) -> Result<()> {
+    show(
+        edit_domain_view(entry),
+        &metadata(response_url),
+        trigger_id,
+        token,
+    )
+}
+
+fn edit_description_view(entry: &Entry) -> View {
+    View::modal(
+        callback::encode(&callback::View::EditDescription { ip: entry.ip.clone() }),
+        "설명 추가/수정",
+        vec![
+            Block::input(
+                "description",
+                "설명",
+                Element::PlainTextInput(
+                    PlainTextInput::new("description", entry.description.clone()).multiline(),
+                ),
+                true,
+            ),
+        ],
+    )
+}
+
+pub fn show_edit_description_modal(
+    entry: &Entry,
+    trigger_id: &str,
+    response_url: Option<&str>,
+    token: &str,
+) -> Result<()> {
+    show(
+        edit_description_view(entry),
+        &metadata(response_url),
+        trigger_id,
+        token,
+    )
+}
+
+pub fn show_edit_port_modal(
+    ip: &str,
+    port: &str,
+    trigger_id: &str,
+    response_url: Option<&str>,
+    token: &str,
+) -> Result<()> {
+    show(
+        View::modal(
+            callback::encode(&callback::View::EditPort { ip: ip.to_owned() }),
+            "포트 수정",
+            vec![text_input("port", "포트", Some(port.to_owned()), true)],
+        ),
+        &Metadata {
+            port: port.parse().ok(),
+            ..metadata(response_url)
+        },
+        trigger_id,
+        token,
+    )
+}
+
+pub fn show_add_port_modal(
+    ip: &str,
+    trigger_id: &str,
+    response_url: Option<&str>,
+    token: &str,
+) -> Result<()> {
+    show(
+        View::modal(
+            callback::encode(&callback::View::AddPort { ip: ip.to_owned() }),
+            "포트 추가",
+            (1..5)
+                .map(|i| {
+                    text_input(
+                        &format!("new_port_{}", i),
+                        &format!("포트 {}", i),
+                        None,
+                        i > 1,
+                    )
+                })
+                .collect(),
+        ),
+        &metadata(response_url),
+        trigger_id,
+        token,
+    )
+}
+
+fn entry_blocks(entry: &Entry) -> Vec<Block> {
+    let using = OptionObject::new("사용중", "using");
+    vec![
+        text_input("domain", "도메인", entry.domain.clone(), true),
+        Block::input(
+            "description",
+            "설명",
+            Element::PlainTextInput(
+                PlainTextInput::new("description", entry.description.clone()).multiline(),
+            ),
+            true,
+        ),
+        text_input(
+            "ports",
+            "개방 포트 (쉼표로 구분)",
+            Some(format_ports(&entry.open_ports)),
+            true,
+        ),
+        text_input(
+            "scan_ports",
+            "검사할 포트 (쉼표로 구분, 비우면 기본값)",
+            Some(format_ports(&entry.scan_ports)),
+            true,
+        ),
+        Block::input(
+            "using",
+            "상태",
+            Element::Checkboxes(Checkboxes {
+                action_id: "using".to_owned(),
+                options: vec![using.clone()],
+                initial_options: if entry.using { vec![using] } else { vec![] },
+            }),
+            true,
+        ),
+        text_input(
+            "tags",
+            "태그 (쉼표로 구분)",
+            Some(entry.tags.join(", ")),
+            true,
+        ),
+        Block::input(
+            "owner",
+            "담당자",
+            Element::UsersSelect(UsersSelect {
+                action_id: "owner".to_owned(),
+                initial_user: entry.owner.clone(),
+            }),
+            true,
+        ),
+    ]
+}
+
+fn edit_entry_view(entry: &Entry) -> View {
+    View::modal(
+        callback::encode(&callback::View::EditEntry { ip: entry.ip.clone() }),
+        &format!("{} 수정", entry.ip),
+        entry_blocks(entry),
+    )
+}
+
+pub fn show_edit_entry_modal(
+    entry: &Entry,
+    trigger_id: &str,
+    response_url: Option<&str>,
+    token: &str,
+) -> Result<()> {
+    show(
+        edit_entry_view(entry),
+        &metadata(response_url),
+        trigger_id,
+        token,
+    )
+}
+
+fn register_entry_view(draft: &Entry) -> View {
+    let mut blocks = vec![text_input("ip", "IP", None, false)];
+    blocks.extend(entry_blocks(draft));
+    View::modal(
+        callback::encode(&callback::View::RegisterEntry),
+        "IP 등록",
+        blocks,
+    )
+}
+
+/// `draft` only provides the initial values; the IP is typed in by the user.
+pub fn show_register_entry_modal(draft: &Entry, trigger_id: &str, token: &str) -> Result<()> {
+    show(
+        register_entry_view(draft),
+        &Metadata::default(),
+        trigger_id,
+        token,
+    )
+}
+
+pub fn show_search_modal(trigger_id: &str, token: &str) -> Result<()> {
+    show(
+        View::modal(
+            callback::encode(&callback::View::Search),
+            "IP 검색",
+            vec![text_input("query", "검색어", None, false)],
+        ),
+        &Metadata::default(),
+        trigger_id,
+        token,
+    )
+}
+
+/// `action_id` tells the options load URL which entries to suggest.
+fn entry_select(block_id: &str, label: &str, action_id: &str, optional: bool) -> Block {
+    Block::input(
+        block_id,
+        label,
+        Element::ExternalSelect(ExternalSelect {
+            action_id: action_id.to_owned(),
+            placeholder: Some(Text::plain("IP 또는 도메인으로 찾기")),
+            min_query_length: 0,
+        }),
+        optional,
+    )
+}
+
+/// Either an unused entry is taken over, or a free address of a subnet is registered.
+fn allocate_view(subnets: &[String]) -> View {
+    let mut blocks = vec![entry_select("ip", "미사용 IP", "unused_ip", true)];
+    if !subnets.is_empty() {
+        blocks.push(Block::input(
+            "subnet",
+            "또는 서브넷의 빈 IP",
+            Element::StaticSelect(StaticSelect {
+                action_id: "subnet".to_owned(),
+                placeholder: None,
+                options: subnets
+                    .iter()
+                    .take(100)
+                    .map(|subnet| OptionObject::new(subnet.clone(), subnet))
+                    .collect(),
+                initial_option: None,
+            }),
+            true,
+        ));
+    }
+    blocks.push(text_input("description", "설명", None, true));
+    View::modal(
+        callback::encode(&callback::View::Allocate),
+        "IP 할당",
+        blocks,
+    )
+}
+
+pub fn show_allocate_modal(subnets: &[String], trigger_id: &str, token: &str) -> Result<()> {
+    show(allocate_view(subnets), &Metadata::default(), trigger_id, token)
+}
+
+fn transfer_view() -> View {
+    View::modal(
+        callback::encode(&callback::View::Transfer),
+        "담당자 변경",
+        vec![
+            entry_select("ip", "IP", "transferable_ip", false),
+            Block::input(
+                "owner",
+                "새 담당자",
+                Element::UsersSelect(UsersSelect {
+                    action_id: "owner".to_owned(),
+                    initial_user: None,
+                }),
+                false,
+            ),
+        ],
+    )
+}
+
+pub fn show_transfer_modal(trigger_id: &str, token: &str) -> Result<()> {
+    show(transfer_view(), &Metadata::default(), trigger_id, token)
+}
+
+/// Only the first 100 entries can be selected.
+fn bulk_view(entries: &[Entry]) -> View {
+    let shown = &entries[..entries.len().min(100)];
+    let mut blocks = shown
+        .chunks(10)
+        .enumerate()
+        .map(|(i, chunk)| {
+            Block::input(
+                &format!("entries_{}", i),
+                &format!("대상 IP ({}-{})", i * 10 + 1, i * 10 + chunk.len()),
+                Element::Checkboxes(Checkboxes {
+                    action_id: "entries".to_owned(),
+                    options: chunk.iter().map(message::generate_entry_option).collect(),
+                    initial_options: vec![],
+                }),
+                true,
+            )
+        })
+        .collect::<Vec<_>>();
+    if entries.len() > shown.len() {
+        blocks.push(Block::context(vec![
+            Text::plain(format!(
+                "검색 결과 {}개 중 처음 {}개만 선택할 수 있습니다.",
+                entries.len(),
+                shown.len()
+            )),
+        ]));
+    }
+    blocks.extend(vec![
+        Block::input(
+            "operation",
+            "작업",
+            Element::StaticSelect(StaticSelect {
+                action_id: "operation".to_owned(),
+                placeholder: None,
+                options: vec![
+                    OptionObject::new("반납", "release"),
+                    OptionObject::new("삭제", "delete"),
+                    OptionObject::new("태그 추가", "tag"),
+                    OptionObject::new("담당자 변경", "owner"),
+                ],
+                initial_option: None,
+            }),
+            false,
+        ),
+        text_input("tags", "추가할 태그 (쉼표로 구분)", None, true),
+        Block::input(
+            "owner",
+            "새 담당자",
+            Element::UsersSelect(UsersSelect {
+                action_id: "owner".to_owned(),
+                initial_user: None,
+            }),
+            true,
+        ),
+    ]);
+    View::modal(
+        callback::encode(&callback::View::Bulk),
+        "일괄 작업",
+        blocks,
+    )
+}
+
+pub fn show_bulk_modal(
+    entries: &[Entry],
+    page: &Page,
+    trigger_id: &str,
+    response_url: Option<&str>,
+    token: &str,
+) -> Result<()> {
+    show(
+        bulk_view(entries),
+        &Metadata {
+            page: Some(page.clone()),
+            ..metadata(response_url)
+        },
+        trigger_id,
+        token,
+    )
+}
+
+/// `metadata.bulk` must be set.
+pub fn generate_bulk_confirm(metadata: &Metadata) -> String {
+    let bulk = match metadata.bulk {
+        Some(ref bulk) => bulk,
+        None => return generate_expired(),
+    };
+    let mut lines = vec![format!(
+        "*{}* 작업을 IP {}개에 적용합니다.",
+        bulk.operation.label(),
+        bulk.ips.len()
+    )];
+    match bulk.operation {
+        Operation::Delete => lines.push("삭제된 정보는 복구할 수 없습니다.".to_owned()),
+        Operation::Tag { ref tags } => lines.push(format!("태그: {}", escape(&tags.join(", ")))),
+        Operation::Owner { ref owner } => lines.push(format!("새 담당자: <@{}>", owner)),
+        Operation::Release => (),
+    }
+    if !bulk.gone.is_empty() {
+        lines.push(format!(
+            "선택한 IP 중 {}은(는) 그 사이에 삭제되어 제외했습니다.",
+            bulk.gone.join(", ")
+        ));
+    }
+    lines.push(
+        bulk.ips
+            .iter()
+            .map(|ip| format!("`{}`", ip))
+            .collect::<Vec<_>>()
+            .join(" "),
+    );
+    serde_json::to_string(&json!({
+        "response_action": "update",
+        "view": View::modal(
+            callback::encode(&callback::View::BulkConfirm),
+            "일괄 작업 확인",
+            vec![Block::section(Text::mrkdwn(lines.join("\n")))],
+        ).private_metadata(serde_json::to_string(metadata).unwrap_or_default()),
+    })).unwrap_or_default()
+}
+
+pub fn generate_search_result(query: &str, entries: &[Entry]) -> String {
+    let mut blocks = vec![
+        Block::section(Text::plain(format!(
+            "{} 검색 결과 {}건",
+            query,
+            entries.len()
+        ))),
+    ];
+    let fields = message::generate_list_fields(&entries[..entries.len().min(10)]);
+    if !fields.is_empty() {
+        blocks.push(Block::fields(fields));
+    }
+    serde_json::to_string(&json!({
+        "response_action": "update",
+        "view": View::modal("search_result", "IP 검색", blocks).without_submit(),
+    })).unwrap_or_default()
+}
+
+fn generate_notice(title: &str, text: &str) -> String {
+    serde_json::to_string(&json!({
+        "response_action": "update",
+        "view": View::modal("notice", title, vec![Block::section(Text::plain(text))])
+            .without_submit(),
+    })).unwrap_or_default()
+}
+
+pub fn generate_denied() -> String {
+    generate_notice(
+        "권한 없음",
+        "죄송합니다. 이 작업은 담당자나 관리자만 할 수 있습니다.",
+    )
+}
+
+pub fn generate_inexist() -> String {
+    generate_notice("IP 없음", "존재하지 않는 IP 입니다.")
+}
+
+pub fn generate_expired() -> String {
+    generate_notice("만료됨", "만료된 창입니다. 명령을 다시 실행해 주세요.")
+}
+
+pub fn generate_errors(errors: &[(String, String)]) -> String {
+    let errors = errors
+        .iter()
+        .map(|&(ref block_id, ref message)| (block_id.clone(), json!(message)))
+        .collect::<serde_json::Map<_, _>>();
+    serde_json::to_string(&json!({
+        "response_action": "errors",
+        "errors": errors,
+    })).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slack::tests::{assert_escaped, hostile_entry, HOSTILE};
+
+    fn to_json(view: &View) -> String {
+        serde_json::to_string(view).unwrap()
+    }
+
+    #[test]
+    fn entry_modals_keep_plain_text() {
+        assert_escaped(&to_json(&edit_entry_view(&hostile_entry("10.0.0.1"))));
+        assert_escaped(&to_json(&register_entry_view(&hostile_entry("10.0.0.1"))));
+        assert_escaped(&to_json(&edit_domain_view(&hostile_entry("10.0.0.1"))));
+        assert_escaped(&to_json(&edit_description_view(&hostile_entry("10.0.0.1"))));
+    }
+
+    #[test]
+    fn edit_entry_prefills_scan_ports() {
+        let mut entry = Entry::draft("10.0.0.1", ::std::path::Path::new("."));
+        entry.scan_ports = vec![22, 8080];
+        let view = serde_json::to_value(&edit_entry_view(&entry)).unwrap();
+        let block = view["blocks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|block| block["block_id"] == "scan_ports")
+            .unwrap()
+            .clone();
+        assert_eq!(block["element"]["initial_value"], "22, 8080");
+    }
+
+    #[test]
+    fn bulk_modals_escape_user_text() {
+        assert_escaped(&to_json(&bulk_view(&[hostile_entry("10.0.0.1")])));
+        let metadata = Metadata {
+            bulk: Some(Bulk {
+                ips: vec!["10.0.0.1".to_owned()],
+                operation: Operation::Tag {
+                    tags: vec![HOSTILE.to_owned()],
+                },
+                gone: vec![],
+            }),
+            ..Metadata::default()
+        };
+        assert_escaped(&generate_bulk_confirm(&metadata));
+    }
+
+    fn external_selects(view: &View) -> Vec<String> {
+        let view = serde_json::to_value(view).unwrap();
+        view["blocks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|block| block["element"]["type"] == "external_select")
+            .map(|block| block["element"]["action_id"].as_str().unwrap().to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn allocation_and_transfer_pick_entries_by_type_ahead() {
+        assert_eq!(external_selects(&allocate_view(&[])), vec!["unused_ip"]);
+        let view = serde_json::to_value(&allocate_view(&["10.0.0.0/24".to_owned()])).unwrap();
+        assert_eq!(view["blocks"][1]["block_id"], "subnet");
+        assert_eq!(external_selects(&transfer_view()), vec!["transferable_ip"]);
+    }
+
+    #[test]
+    fn bulk_confirm_names_deleted_ips() {
+        let metadata = Metadata {
+            bulk: Some(Bulk {
+                ips: vec!["10.0.0.1".to_owned()],
+                operation: Operation::Release,
+                gone: vec!["10.0.0.2".to_owned()],
+            }),
+            ..Metadata::default()
+        };
+        let confirm = generate_bulk_confirm(&metadata);
+        assert!(confirm.contains("IP 1개"));
+        assert!(confirm.contains("10.0.0.2은(는) 그 사이에 삭제되어"));
+    }
+
+    #[test]
+    fn search_result_escapes_user_text() {
+        let entries = vec![hostile_entry("10.0.0.1"), hostile_entry("10.0.0.2")];
+        assert_escaped(&generate_search_result(HOSTILE, &entries));
+        assert_escaped(&generate_search_result(HOSTILE, &[]));
+    }
+}
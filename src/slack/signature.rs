@@ -0,0 +1,106 @@
+extern crate hmac;
+extern crate sha2;
+
+use self::hmac::{Hmac, Mac};
+use self::sha2::Sha256;
+
+const MAX_AGE: u64 = 60 * 5;
+
+pub struct Signature {
+    pub timestamp: String,
+    pub signature: String,
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| s.get(i..i + 2).and_then(|b| u8::from_str_radix(b, 16).ok()))
+        .collect()
+}
+
+impl Signature {
+    pub fn verify(&self, secret: &str, body: &str, now: u64) -> bool {
+        let timestamp = match self.timestamp.parse::<u64>() {
+            Ok(timestamp) => timestamp,
+            Err(_) => return false,
+        };
+        if now.saturating_sub(timestamp) > MAX_AGE || timestamp.saturating_sub(now) > MAX_AGE {
+            return false;
+        }
+        let code = match self.signature
+            .trim()
+            .splitn(2, '=')
+            .collect::<Vec<_>>()
+            .as_slice()
+        {
+            ["v0", hex] => match decode_hex(hex) {
+                Some(code) => code,
+                None => return false,
+            },
+            _ => return false,
+        };
+        let mut mac = match Hmac::<Sha256>::new_varkey(secret.as_bytes()) {
+            Ok(mac) => mac,
+            Err(_) => return false,
+        };
+        mac.input(format!("v0:{}:{}", self.timestamp, body).as_bytes());
+        mac.verify(&code).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The example from Slack's "Verifying requests from Slack" documentation.
+    const SECRET: &str = "8f742231b10e8888abcd99yyyzzz85a5";
+    const TIMESTAMP: u64 = 1_531_420_618;
+    const BODY: &str = "token=xyzz0WbapA4vBCDEFasx0q6G&team_id=T1DC2JH3J&team_domain=testteamnow&\
+                        channel_id=G8PSS9T3V&channel_name=foobar&user_id=U2CERLKJA&\
+                        user_name=roadrunner&command=%2Fwebhook-collect&text=&\
+                        response_url=https%3A%2F%2Fhooks.slack.com%2Fcommands%2FT1DC2JH3J%2F\
+                        397700885554%2F96rGlfmibIGlgcZRskXaIFfN&\
+                        trigger_id=398738663015.47445629121.803a0bc887a14d10d2c447fce8b6703c";
+
+    fn signature() -> Signature {
+        Signature {
+            timestamp: format!("{}", TIMESTAMP),
+            signature: "v0=a2114d57b48eac39b9ad189dd8316235a7b4a8d21a10bd27519666489c69b503"
+                .to_owned(),
+        }
+    }
+
+    #[test]
+    fn accepts_slack_example() {
+        assert!(signature().verify(SECRET, BODY, TIMESTAMP + 10));
+    }
+
+    #[test]
+    fn rejects_tampered_body() {
+        let body = BODY.replace("text=", "text=release");
+        assert!(!signature().verify(SECRET, &body, TIMESTAMP));
+        assert!(!signature().verify("another secret", BODY, TIMESTAMP));
+    }
+
+    #[test]
+    fn rejects_stale_timestamp() {
+        assert!(!signature().verify(SECRET, BODY, TIMESTAMP + MAX_AGE + 1));
+        assert!(!signature().verify(SECRET, BODY, TIMESTAMP - MAX_AGE - 1));
+        let mut overflowing = signature();
+        overflowing.timestamp = format!("{}", u64::max_value());
+        assert!(!overflowing.verify(SECRET, BODY, TIMESTAMP));
+        assert!(!signature().verify(SECRET, BODY, u64::max_value()));
+    }
+
+    #[test]
+    fn rejects_malformed_signature() {
+        let mut signature = signature();
+        signature.signature = "v1=a2114d57".to_owned();
+        assert!(!signature.verify(SECRET, BODY, TIMESTAMP));
+        signature.signature = "v0=xyz".to_owned();
+        assert!(!signature.verify(SECRET, BODY, TIMESTAMP));
+    }
+}
@@ -14,7 +14,11 @@ struct Request {
 #[serde(tag = "type")]
 pub enum Submission {
     #[serde(rename = "interactive_message")] Interactive(Interactive),
-    #[serde(rename = "dialog_submission")] Dialog(Dialog),
+    #[serde(rename = "block_actions")] BlockActions(BlockActions),
+    #[serde(rename = "view_submission")] ViewSubmission(ViewSubmission),
+    #[serde(rename = "block_suggestion")] BlockSuggestion(BlockSuggestion),
+    #[serde(rename = "shortcut")] Shortcut(Shortcut),
+    #[serde(rename = "message_action")] MessageAction(MessageAction),
 }
 
 impl FromStr for Submission {
@@ -34,14 +38,31 @@ impl Submission {
     pub fn from_str(s: &str) -> Result<Self> {
         FromStr::from_str(s)
     }
+
+    pub fn token(&self) -> &str {
+        match *self {
+            Submission::Interactive(ref interactive) => &interactive.token,
+            Submission::BlockActions(ref block_actions) => &block_actions.token,
+            Submission::ViewSubmission(ref view_submission) => &view_submission.token,
+            Submission::BlockSuggestion(ref block_suggestion) => &block_suggestion.token,
+            Submission::Shortcut(ref shortcut) => &shortcut.token,
+            Submission::MessageAction(ref message_action) => &message_action.token,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct User {
+    pub id: String,
 }
 
 #[derive(Deserialize, Debug)]
 pub struct Interactive {
     pub actions: Vec<Action>,
+    pub user: User,
     pub callback_id: String,
     pub message_ts: String,
-    pub token: String,
+    #[serde(default)] pub token: String,
     pub response_url: String,
     pub trigger_id: String,
 }
@@ -54,8 +75,208 @@ pub struct Action {
 }
 
 #[derive(Deserialize, Debug)]
-pub struct Dialog {
-    pub submission: HashMap<String, Option<String>>,
+pub struct BlockActions {
+    pub actions: Vec<BlockAction>,
+    pub user: User,
+    #[serde(default)] pub token: String,
+    pub response_url: Option<String>,
+    pub trigger_id: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct BlockAction {
+    pub action_id: String,
+    pub block_id: String,
+    #[serde(default)] pub value: String,
+    pub selected_option: Option<SelectedOption>,
+}
+
+impl BlockAction {
+    pub fn name(&self) -> &str {
+        self.action_id.split('-').next().unwrap_or_default()
+    }
+
+    /// Selects carry their value in the chosen option instead.
+    pub fn value(&self) -> &str {
+        self.selected_option
+            .as_ref()
+            .map(|option| option.value.as_str())
+            .unwrap_or(&self.value)
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct BlockSuggestion {
+    #[serde(default)] pub token: String,
+    pub action_id: String,
+    pub user: User,
+    #[serde(default)] pub value: String,
+}
+
+/// A global shortcut. `callback_id` is the one configured for the shortcut in the app.
+#[derive(Deserialize, Debug)]
+pub struct Shortcut {
+    #[serde(default)] pub token: String,
     pub callback_id: String,
-    pub token: String,
+    pub user: User,
+    pub trigger_id: String,
+}
+
+/// A message shortcut.
+#[derive(Deserialize, Debug)]
+pub struct MessageAction {
+    #[serde(default)] pub token: String,
+    pub callback_id: String,
+    pub response_url: String,
+    pub message: ShortcutMessage,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ShortcutMessage {
+    #[serde(default)] pub text: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ViewSubmission {
+    #[serde(default)] pub token: String,
+    pub user: User,
+    pub view: SubmittedView,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SubmittedView {
+    pub callback_id: String,
+    #[serde(default)] pub private_metadata: String,
+    pub state: ViewState,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ViewState {
+    pub values: HashMap<String, HashMap<String, ViewValue>>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ViewValue {
+    pub value: Option<String>,
+    pub selected_option: Option<SelectedOption>,
+    #[serde(default)] pub selected_options: Vec<SelectedOption>,
+    pub selected_user: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SelectedOption {
+    pub value: String,
+}
+
+impl ViewSubmission {
+    fn input(&self, block_id: &str) -> Option<&ViewValue> {
+        self.view
+            .state
+            .values
+            .get(block_id)
+            .and_then(|actions| actions.values().next())
+    }
+
+    pub fn value(&self, block_id: &str) -> Option<&str> {
+        self.input(block_id)
+            .and_then(|input| input.value.as_ref())
+            .map(|value| value.trim())
+            .filter(|value| !value.is_empty())
+    }
+
+    pub fn selected_option(&self, block_id: &str) -> Option<&str> {
+        self.input(block_id)
+            .and_then(|input| input.selected_option.as_ref())
+            .map(|option| option.value.as_str())
+    }
+
+    pub fn selected_user(&self, block_id: &str) -> Option<&str> {
+        self.input(block_id)
+            .and_then(|input| input.selected_user.as_ref())
+            .map(String::as_str)
+    }
+
+    pub fn selected(&self, block_id: &str) -> Vec<&str> {
+        self.input(block_id)
+            .map(|input| {
+                input
+                    .selected_options
+                    .iter()
+                    .map(|option| option.value.as_str())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ip::Entry;
+    use slack::callback::{self, Action as Callback};
+    use slack::message::generate_open_select;
+    use std::path::Path;
+
+    fn request(payload: &serde_json::Value) -> String {
+        serde_urlencoded::to_string(&[("payload", payload.to_string())]).unwrap()
+    }
+
+    #[test]
+    fn block_actions_prefer_the_selected_option() {
+        let entries = vec![Entry::draft("10.0.0.1", Path::new("/nonexistent"))];
+        let select = serde_json::to_value(&generate_open_select(&entries)).unwrap();
+        let payload = json!({
+            "type": "block_actions",
+            "user": { "id": "U1", "name": "user" },
+            "token": "token",
+            "response_url": "https://hooks.slack.com/actions/1",
+            "trigger_id": "1.2.3",
+            "actions": [{
+                "type": "static_select",
+                "action_id": select["elements"][0]["action_id"],
+                "block_id": select["block_id"],
+                "selected_option": {
+                    "text": { "type": "plain_text", "text": "10.0.0.1" },
+                    "value": "10.0.0.1",
+                },
+                "action_ts": "1.2",
+            }],
+        });
+
+        let block_actions = match Submission::from_str(&request(&payload)) {
+            Ok(Submission::BlockActions(block_actions)) => block_actions,
+            _ => panic!("expected block_actions"),
+        };
+        assert_eq!(block_actions.token, "token");
+        let action = &block_actions.actions[0];
+        assert_eq!(action.name(), "open_ip");
+        assert_eq!(action.value(), "10.0.0.1");
+        match callback::decode(&action.block_id) {
+            Some(Callback::Open) => {}
+            _ => panic!("expected the open callback"),
+        }
+    }
+
+    #[test]
+    fn buttons_keep_their_own_value() {
+        let payload = json!({
+            "type": "block_actions",
+            "user": { "id": "U1", "name": "user" },
+            "trigger_id": "1.2.3",
+            "actions": [{
+                "type": "button",
+                "action_id": "edit_port-22",
+                "block_id": "b",
+                "value": "22",
+            }],
+        });
+        match Submission::from_str(&request(&payload)) {
+            Ok(Submission::BlockActions(block_actions)) => {
+                assert!(block_actions.response_url.is_none());
+                assert_eq!(block_actions.actions[0].name(), "edit_port");
+                assert_eq!(block_actions.actions[0].value(), "22");
+            }
+            _ => panic!("expected block_actions"),
+        }
+    }
 }
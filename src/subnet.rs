@@ -0,0 +1,100 @@
+use std::net::Ipv4Addr;
+use std::str::FromStr;
+use errors::{Error, Result};
+
+/// Subnets in the settings may not be larger than this, so that they can be swept.
+pub const MIN_PREFIX: u32 = 16;
+
+#[derive(Clone, Copy)]
+pub struct Subnet {
+    network: u32,
+    prefix: u32,
+}
+
+impl Subnet {
+    pub fn from_str(s: &str) -> Result<Self> {
+        FromStr::from_str(s)
+    }
+
+    fn mask(&self) -> u32 {
+        if self.prefix == 0 {
+            0
+        } else {
+            !0 << (32 - self.prefix)
+        }
+    }
+
+    pub fn contains(&self, ip: &str) -> bool {
+        ip.parse::<Ipv4Addr>()
+            .map(|ip| u32::from(ip) & self.mask() == self.network)
+            .unwrap_or(false)
+    }
+
+    pub fn prefix(&self) -> u32 {
+        self.prefix
+    }
+
+    pub fn hosts(&self) -> impl Iterator<Item = String> {
+        let (first, last) = if self.prefix >= 31 {
+            (self.network, self.network | !self.mask())
+        } else {
+            (self.network + 1, (self.network | !self.mask()) - 1)
+        };
+        (u64::from(first)..u64::from(last) + 1).map(|ip| format!("{}", Ipv4Addr::from(ip as u32)))
+    }
+
+    pub fn size(&self) -> usize {
+        let size = 1usize << (32 - self.prefix);
+        if self.prefix >= 31 {
+            size
+        } else {
+            size - 2
+        }
+    }
+}
+
+impl FromStr for Subnet {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut split = s.trim().splitn(2, '/');
+        let ip = split
+            .next()
+            .and_then(|ip| ip.parse::<Ipv4Addr>().ok())
+            .ok_or_else(|| Error::from(format!("invalid subnet: {}", s)))?;
+        let prefix = split
+            .next()
+            .map(|p| p.parse::<u32>().ok().filter(|p| *p <= 32))
+            .unwrap_or(Some(32))
+            .ok_or_else(|| Error::from(format!("invalid subnet: {}", s)))?;
+        let mut subnet = Subnet { network: 0, prefix };
+        subnet.network = u32::from(ip) & subnet.mask();
+        Ok(subnet)
+    }
+}
+
+impl ::std::fmt::Display for Subnet {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        write!(f, "{}/{}", Ipv4Addr::from(self.network), self.prefix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hosts_skip_network_and_broadcast() {
+        let subnet = Subnet::from_str("10.0.0.7/30").unwrap();
+        assert_eq!(subnet.hosts().collect::<Vec<_>>(), vec!["10.0.0.5", "10.0.0.6"]);
+        assert_eq!(subnet.size(), 2);
+        let subnet = Subnet::from_str("10.0.0.7/32").unwrap();
+        assert_eq!(subnet.hosts().collect::<Vec<_>>(), vec!["10.0.0.7"]);
+    }
+
+    #[test]
+    fn hosts_are_lazy() {
+        let subnet = Subnet::from_str("0.0.0.0/0").unwrap();
+        assert_eq!(subnet.hosts().nth(1), Some("0.0.0.2".to_owned()));
+    }
+}
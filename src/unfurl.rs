@@ -0,0 +1,39 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use regex;
+
+pub fn extract_ips(text: &str) -> Vec<String> {
+    lazy_static! {
+        static ref REGEX_IP: regex::Regex =
+            regex::Regex::new(r"\b\d{1,3}\.\d{1,3}\.\d{1,3}\.\d{1,3}\b").unwrap();
+    }
+
+    let mut ips: Vec<String> = vec![];
+    for m in REGEX_IP.find_iter(text) {
+        if !ips.iter().any(|ip| ip == m.as_str()) {
+            ips.push(m.as_str().to_owned());
+        }
+    }
+    ips
+}
+
+#[derive(Default)]
+pub struct Limiter {
+    last: Mutex<HashMap<(String, String), u64>>,
+}
+
+impl Limiter {
+    pub fn allow(&self, channel: &str, ip: &str, now: u64, cooldown: u64) -> bool {
+        let mut last = match self.last.lock() {
+            Ok(last) => last,
+            Err(_) => return false,
+        };
+        last.retain(|_, t| *t + cooldown > now);
+        let key = (channel.to_owned(), ip.to_owned());
+        if last.contains_key(&key) {
+            return false;
+        }
+        last.insert(key, now);
+        true
+    }
+}